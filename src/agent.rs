@@ -0,0 +1,59 @@
+use std::process::Command;
+
+use log::debug;
+
+// the output of every step is sent back to the model, so it's cut down to the last few lines
+const MAX_OUTPUT_CHARS: usize = 2000;
+
+pub struct StepOutput {
+    pub exit_status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl StepOutput {
+    // runs the command through sh like every other command, but holds on to its output
+    pub fn run(cmd: &str) -> StepOutput {
+        debug!("running agent step {}", cmd);
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(cmd)
+            .output()
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        StepOutput {
+            exit_status: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+    }
+
+    fn truncate(output: &str) -> String {
+        let chars = output.chars().count();
+        match chars > MAX_OUTPUT_CHARS {
+            true => format!(
+                "...(truncated)\n{}",
+                output
+                    .chars()
+                    .skip(chars - MAX_OUTPUT_CHARS)
+                    .collect::<String>()
+            ),
+            false => output.to_string(),
+        }
+    }
+
+    pub fn get_user_message(&self, cmd: &str) -> String {
+        let exit_status = match self.exit_status {
+            Some(code) => code.to_string(),
+            None => "killed by a signal".to_string(),
+        };
+
+        format!(
+            "command -> {}\nexit status -> {}\nstdout ->\n{}\nstderr ->\n{}",
+            cmd,
+            exit_status,
+            StepOutput::truncate(self.stdout.trim_end()),
+            StepOutput::truncate(self.stderr.trim_end())
+        )
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Local;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::models::*;
+use crate::script::Script;
+use crate::storage::get_data_dir;
+use crate::verify::Verify;
+
+// keywords and the builtins of bash and zsh that Verify doesn't know about, a function can't
+// stand in for any of them
+const SHELL_WORDS: [&str; 35] = [
+    "autoload", "bind", "bindkey", "break", "caller", "case", "compgen", "complete", "continue",
+    "coproc", "disown", "do", "done", "elif", "else", "enable", "esac", "fi", "for", "function",
+    "getopts", "if", "in", "logout", "print", "readonly", "return", "select", "setopt", "shopt",
+    "then", "typeset", "ulimit", "until", "while",
+];
+
+// a suggestion the user liked enough to give it a name, the hook turns it into a shell function
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Alias {
+    pub name: String,
+    pub created_at: String,
+    pub suggestion: ModelSuggestion,
+}
+
+impl Alias {
+    fn get_library_path() -> PathBuf {
+        get_data_dir().join("aliases.json")
+    }
+
+    // the name ends up as a shell function name, so it is kept to what every shell accepts
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit() || c == '-')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    // a name that already runs something would be shadowed, and `git() { git ...; }` calls
+    // itself forever
+    pub fn is_taken(name: &str) -> bool {
+        SHELL_WORDS.contains(&name) || Verify::binary_exists(name)
+    }
+
+    pub fn load_all() -> Vec<Alias> {
+        let path = Alias::get_library_path();
+        let buf = match fs::read_to_string(&path) {
+            Ok(buf) => buf,
+            Err(_) => return vec![],
+        };
+
+        match serde_json::from_str::<Vec<Alias>>(&buf) {
+            Ok(aliases) => aliases,
+            Err(e) => {
+                warn!("ignoring bad alias library {}: {}", path.display(), e);
+                vec![]
+            }
+        }
+    }
+
+    fn save_all(aliases: &[Alias]) {
+        let path = Alias::get_library_path();
+        debug!("saving {} aliases to {}", aliases.len(), path.display());
+
+        let aliases_string =
+            serde_json::to_string_pretty(aliases).unwrap_or_else(|e| panic!("{}", e));
+        fs::write(path, aliases_string).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    // saving under an existing name replaces the old entry
+    pub fn add(name: &str, suggestion: &ModelSuggestion) {
+        let mut aliases = Alias::load_all();
+        aliases.retain(|alias| alias.name != name);
+        aliases.push(Alias {
+            name: name.to_string(),
+            created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            suggestion: suggestion.clone(),
+        });
+        aliases.sort_by(|a, b| a.name.cmp(&b.name));
+        Alias::save_all(&aliases);
+    }
+
+    pub fn remove(name: &str) -> bool {
+        let mut aliases = Alias::load_all();
+        let count = aliases.len();
+        aliases.retain(|alias| alias.name != name);
+        if aliases.len() == count {
+            return false;
+        }
+
+        Alias::save_all(&aliases);
+        true
+    }
+
+    // missing fields become the function arguments in order, quoted the same way as in scripts
+    // since the function ends up in the rc. the commands are chained with && so the function
+    // stops at the first one that fails, like a normal run does
+    pub fn get_function(&self) -> String {
+        let fields = Script::get_fields(&self.suggestion);
+        let usage = fields
+            .iter()
+            .map(|field| Script::get_variable_name(&field.key))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        let mut function = format!("# {}\n{}() {{\n", self.suggestion.reasoning, self.name);
+        for (i, field) in fields.iter().enumerate() {
+            function.push_str(&format!(
+                "    local {}=\"${{{}:?usage: {} {}}}\"\n",
+                Script::get_variable_name(&field.key),
+                i + 1,
+                self.name,
+                usage
+            ));
+        }
+
+        let commands = self
+            .suggestion
+            .commands
+            .iter()
+            .map(|command| format!("    {}", Script::get_command(command)))
+            .collect::<Vec<String>>()
+            .join(" &&\n");
+        function.push_str(&commands);
+        function.push_str("\n}\n");
+        function
+    }
+
+    pub fn get_functions(aliases: &[Alias]) -> String {
+        aliases
+            .iter()
+            .map(|alias| alias.get_function())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn print_list(aliases: &[Alias]) {
+        if aliases.is_empty() {
+            println!("no aliases saved yet");
+            return;
+        }
+
+        for alias in aliases {
+            println!("{} - {}", alias.name, alias.suggestion.reasoning);
+            for command in &alias.suggestion.commands {
+                println!("    {}", command.cmd);
+            }
+        }
+    }
+}
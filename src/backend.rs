@@ -0,0 +1,166 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::{debug, trace};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::models::*;
+use crate::storage::get_key;
+
+// the backend could not be reached at all, or it answered with something that can't be parsed
+pub enum BackendError {
+    Unavailable(String),
+    Malformed(String),
+}
+
+impl Debug for BackendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::Unavailable(msg) => write!(f, "Unavailable: {}", msg),
+            BackendError::Malformed(msg) => write!(f, "Malformed: {}", msg),
+        }
+    }
+}
+
+impl Display for BackendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::Unavailable(msg) => write!(f, "Backend unavailable: {}", msg),
+            BackendError::Malformed(msg) => {
+                write!(f, "Unable to parse the model response: {}", msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+// anything that can take a chat request and hand back the raw response body
+pub trait Backend {
+    fn get_response(&self, request: &OllamaRequest) -> Result<String, BackendError>;
+
+    // canned and replayed responses say nothing about how a model is actually used
+    fn is_real(&self) -> bool {
+        true
+    }
+}
+
+pub struct OllamaBackend {
+    pub url: String,
+}
+
+impl Backend for OllamaBackend {
+    fn get_response(&self, request: &OllamaRequest) -> Result<String, BackendError> {
+        debug!("sending request to {}", self.url);
+        let response = match Client::new()
+            .post(&self.url)
+            .json(request)
+            .timeout(Duration::from_secs(360))
+            .send()
+        {
+            Ok(response) => response,
+            Err(e) if e.is_connect() => return Err(BackendError::Unavailable(e.to_string())),
+            Err(e) => panic!("{}", e),
+        };
+
+        // we can directly use json() as well, and specify the type after the let
+        // the issue is that the error messages are not clear in that case, thats why it is a 2 step process
+        Ok(response.text().unwrap_or_else(|e| panic!("{}", e)))
+    }
+}
+
+pub struct DummyBackend;
+
+impl Backend for DummyBackend {
+    fn get_response(&self, _request: &OllamaRequest) -> Result<String, BackendError> {
+        Ok(DummyResponse::get_dummy_response())
+    }
+
+    fn is_real(&self) -> bool {
+        false
+    }
+}
+
+// a single recorded request/response pair, stored as <dir>/<key>.json
+#[derive(Serialize, Deserialize)]
+pub struct Cassette {
+    pub key: String,
+    pub request: OllamaRequest,
+    pub response: String,
+}
+
+impl Cassette {
+    // the key only depends on the messages (system prompt + user query), so changing the model or
+    // any other request option still replays the same cassette
+    pub fn key(request: &OllamaRequest) -> String {
+        let parts = request
+            .messages
+            .iter()
+            .flat_map(|message| [message.role.as_str(), message.content.as_str()])
+            .collect::<Vec<&str>>();
+
+        get_key(&parts)
+    }
+
+    pub fn path(dir: &str, key: &str) -> PathBuf {
+        PathBuf::from(dir).join(format!("{}.json", key))
+    }
+}
+
+// wraps another backend and saves every request/response pair it sees
+pub struct RecordingBackend {
+    pub inner: Box<dyn Backend>,
+    pub dir: String,
+}
+
+impl Backend for RecordingBackend {
+    fn get_response(&self, request: &OllamaRequest) -> Result<String, BackendError> {
+        let response = self.inner.get_response(request)?;
+
+        let cassette = Cassette {
+            key: Cassette::key(request),
+            request: request.clone(),
+            response: response.clone(),
+        };
+        let path = Cassette::path(&self.dir, &cassette.key);
+        debug!("recording cassette to {}", path.display());
+
+        fs::create_dir_all(&self.dir).unwrap_or_else(|e| panic!("{}", e));
+        let cassette_string =
+            serde_json::to_string_pretty(&cassette).unwrap_or_else(|e| panic!("{}", e));
+        fs::write(path, cassette_string).unwrap_or_else(|e| panic!("{}", e));
+
+        Ok(response)
+    }
+
+    fn is_real(&self) -> bool {
+        self.inner.is_real()
+    }
+}
+
+// serves responses previously saved by the RecordingBackend
+pub struct ReplayBackend {
+    pub dir: String,
+}
+
+impl Backend for ReplayBackend {
+    fn get_response(&self, request: &OllamaRequest) -> Result<String, BackendError> {
+        let key = Cassette::key(request);
+        let path = Cassette::path(&self.dir, &key);
+        debug!("replaying cassette from {}", path.display());
+
+        let buf = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("no cassette for key {} at {}: {}", key, path.display(), e));
+        let cassette = serde_json::from_str::<Cassette>(&buf).unwrap_or_else(|e| panic!("{}", e));
+        trace!("replayed response is {}", cassette.response);
+
+        Ok(cassette.response)
+    }
+
+    fn is_real(&self) -> bool {
+        false
+    }
+}
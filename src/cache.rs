@@ -0,0 +1,190 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::feedback::Choice;
+use crate::models::*;
+use crate::prompts::Prompts;
+use crate::storage::{get_data_dir, get_key};
+
+// cached responses older than this are ignored and removed
+const TTL_SECS: u64 = 24 * 60 * 60;
+// once there are more entries than this, the oldest ones are evicted
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub created_at: u64,
+    pub query: String,
+    pub model: String,
+    pub prompt_version: String,
+    pub response: OllamaPlaceholderResponse,
+}
+
+pub struct ResponseCache;
+
+impl ResponseCache {
+    fn get_cache_dir() -> PathBuf {
+        let dir = get_data_dir().join("cache");
+        fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("{}", e));
+        dir
+    }
+
+    fn get_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .as_secs()
+    }
+
+    pub fn normalize_query(query: &str) -> String {
+        query
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase()
+    }
+
+    // everything that goes into the prompt apart from the history, which changes with every
+    // single command the user runs. the git state is added on top since staging a file doesn't
+    // always change what the git provider sends
+    pub fn get_context_fingerprint(ctx: &Context) -> String {
+        let mut value = ctx.get_prompt_value();
+        if let Value::Object(fields) = &mut value {
+            fields.remove("history");
+        }
+
+        get_key(&[
+            &value.to_string(),
+            &ResponseCache::get_git_state(Path::new(&ctx.cwd)),
+        ])
+    }
+
+    // HEAD tells us the branch/commit, and the index changes whenever something gets staged
+    fn get_git_state(cwd: &Path) -> String {
+        let git_dir = match cwd
+            .ancestors()
+            .map(|dir| dir.join(".git"))
+            .find(|dir| dir.is_dir())
+        {
+            Some(dir) => dir,
+            None => return String::new(),
+        };
+
+        let head = fs::read_to_string(git_dir.join("HEAD")).unwrap_or_default();
+        let index_modified = fs::metadata(git_dir.join("index"))
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+
+        format!("{}:{}", head.trim(), index_modified)
+    }
+
+    // the snippets and past picks that go into the prompt as examples are part of the key, a new
+    // pick is meant to change the answer
+    pub fn key(
+        query: &str,
+        model: &str,
+        ctx: &Context,
+        snippets: &[ModelSuggestion],
+        examples: &[Choice],
+    ) -> String {
+        let examples = examples
+            .iter()
+            .map(|choice| (&choice.query, &choice.picked))
+            .collect::<Vec<(&String, &Option<ModelSuggestion>)>>();
+        get_key(&[
+            &ResponseCache::normalize_query(query),
+            model,
+            &Prompts::get_version(),
+            &ResponseCache::get_context_fingerprint(ctx),
+            &serde_json::to_string(snippets).unwrap_or_else(|e| panic!("{}", e)),
+            &serde_json::to_string(&examples).unwrap_or_else(|e| panic!("{}", e)),
+        ])
+    }
+
+    pub fn get(key: &str) -> Option<OllamaPlaceholderResponse> {
+        let path = ResponseCache::get_cache_dir().join(format!("{}.json", key));
+        let buf = fs::read_to_string(&path).ok()?;
+
+        let entry = match serde_json::from_str::<CacheEntry>(&buf) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("removing bad cache entry {}: {}", path.display(), e);
+                _ = fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        if ResponseCache::get_now().saturating_sub(entry.created_at) > TTL_SECS {
+            debug!("cache entry {} expired", key);
+            _ = fs::remove_file(&path);
+            return None;
+        }
+
+        debug!("cache hit for {}", key);
+        Some(entry.response)
+    }
+
+    pub fn put(key: &str, query: &str, model: &str, response: &OllamaPlaceholderResponse) {
+        let entry = CacheEntry {
+            created_at: ResponseCache::get_now(),
+            query: ResponseCache::normalize_query(query),
+            model: model.to_string(),
+            prompt_version: Prompts::get_version(),
+            response: response.clone(),
+        };
+
+        let path = ResponseCache::get_cache_dir().join(format!("{}.json", key));
+        debug!("caching response to {}", path.display());
+        let entry_string = serde_json::to_string(&entry).unwrap_or_else(|e| panic!("{}", e));
+        fs::write(path, entry_string).unwrap_or_else(|e| panic!("{}", e));
+
+        ResponseCache::evict();
+    }
+
+    // drops expired entries, then the oldest ones until we are back under MAX_ENTRIES
+    fn evict() {
+        let now = ResponseCache::get_now();
+        let mut entries = fs::read_dir(ResponseCache::get_cache_dir())
+            .unwrap_or_else(|e| panic!("{}", e))
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let created_at = fs::read_to_string(entry.path())
+                    .ok()
+                    .and_then(|buf| serde_json::from_str::<CacheEntry>(&buf).ok())
+                    .map(|cache_entry| cache_entry.created_at)
+                    .unwrap_or_default();
+                (created_at, entry.path())
+            })
+            .collect::<Vec<(u64, PathBuf)>>();
+
+        entries.sort_by_key(|entry| Reverse(entry.0));
+        for (i, (created_at, path)) in entries.iter().enumerate() {
+            if i >= MAX_ENTRIES || now.saturating_sub(*created_at) > TTL_SECS {
+                debug!("evicting cache entry {}", path.display());
+                _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    pub fn clear() -> usize {
+        let entries = fs::read_dir(ResponseCache::get_cache_dir())
+            .unwrap_or_else(|e| panic!("{}", e))
+            .filter_map(|entry| entry.ok())
+            .collect::<Vec<fs::DirEntry>>();
+
+        for entry in &entries {
+            fs::remove_file(entry.path()).unwrap_or_else(|e| panic!("{}", e));
+        }
+
+        entries.len()
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::time::Duration;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{to_value, Value};
+
+use crate::context::{ContextConfig, ContextProvider, ContextProviders, ProviderSettings};
+use crate::models::*;
+
+const COMPOSE_FILES: [&str; 4] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+#[cfg(unix)]
+const DEFAULT_DOCKER_SOCKET: &str = "/var/run/docker.sock";
+// set on the containers kubernetes runs through docker, like with kind or docker desktop
+#[cfg(unix)]
+const POD_LABEL: &str = "io.kubernetes.pod.name";
+
+// only the names, the users section with its tokens and certificates is never read
+#[derive(Deserialize, Default)]
+struct KubeConfigFile {
+    #[serde(rename = "current-context", default)]
+    current_context: Option<String>,
+    #[serde(default)]
+    contexts: Vec<NamedKubeContext>,
+}
+
+#[derive(Deserialize)]
+struct NamedKubeContext {
+    name: String,
+    #[serde(default)]
+    context: KubeContextFields,
+}
+
+#[derive(Deserialize, Default)]
+struct KubeContextFields {
+    #[serde(default)]
+    cluster: String,
+    namespace: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KubeContext {
+    pub name: String,
+    pub cluster: String,
+    pub namespace: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KubernetesContext {
+    pub current_context: Option<String>,
+    pub contexts: Vec<KubeContext>,
+    pub namespaces: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ComposeFile {
+    #[serde(default)]
+    services: HashMap<String, serde_yaml::Value>,
+}
+
+#[cfg(unix)]
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiContainer {
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Container {
+    pub name: String,
+    pub image: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pod: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct DockerContext {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compose_file: Option<String>,
+    pub services: Vec<String>,
+    pub containers: Vec<Container>,
+}
+
+// the contexts and namespaces from the kube config, $KUBECONFIG can point at more than one file
+pub struct KubernetesProvider {
+    settings: ProviderSettings,
+}
+
+impl KubernetesProvider {
+    pub fn load(config: &ContextConfig) -> KubernetesProvider {
+        KubernetesProvider {
+            settings: config.get_settings("kubernetes", 1000, 200),
+        }
+    }
+
+    fn get_paths() -> Vec<PathBuf> {
+        match env::var("KUBECONFIG") {
+            Ok(paths) if !paths.is_empty() => env::split_paths(&paths).collect(),
+            _ => env::var("HOME")
+                .map(|home| vec![PathBuf::from(home).join(".kube").join("config")])
+                .unwrap_or_default(),
+        }
+    }
+
+    fn read_config(path: &Path) -> Option<KubeConfigFile> {
+        let buf = fs::read_to_string(path).ok()?;
+        serde_yaml::from_str::<KubeConfigFile>(&buf)
+            .map_err(|e| warn!("unable to read kube config {}: {}", path.display(), e))
+            .ok()
+    }
+
+    pub fn add_suggestions(suggestions: &mut [ModelSuggestion], ctx: &Context) {
+        let kubernetes = match ctx.get_extra::<KubernetesContext>("kubernetes") {
+            Some(kubernetes) => kubernetes,
+            None => return,
+        };
+
+        let namespaces = kubernetes
+            .namespaces
+            .iter()
+            .map(|namespace| MissingFieldSuggestion {
+                value: namespace.clone(),
+                reasoning: "namespace from the kube config".to_string(),
+            })
+            .collect::<Vec<MissingFieldSuggestion>>();
+        ContextProviders::add_field_suggestions(
+            suggestions,
+            |key| key.contains("namespace"),
+            &namespaces,
+        );
+
+        let contexts = kubernetes
+            .contexts
+            .iter()
+            .map(|context| MissingFieldSuggestion {
+                value: context.name.clone(),
+                reasoning: match kubernetes.current_context.as_ref() == Some(&context.name) {
+                    true => format!("current kube context, cluster {}", context.cluster),
+                    false => format!("kube context for cluster {}", context.cluster),
+                },
+            })
+            .collect::<Vec<MissingFieldSuggestion>>();
+        ContextProviders::add_field_suggestions(
+            suggestions,
+            |key| key.contains("context") || key.contains("cluster"),
+            &contexts,
+        );
+    }
+}
+
+impl ContextProvider for KubernetesProvider {
+    fn name(&self) -> &str {
+        "kubernetes"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, _cwd: &Path) -> Option<Value> {
+        let configs = KubernetesProvider::get_paths()
+            .iter()
+            .filter_map(|path| KubernetesProvider::read_config(path))
+            .collect::<Vec<KubeConfigFile>>();
+        if configs.is_empty() {
+            return None;
+        }
+
+        // like kubectl, the first file to set something wins
+        let mut kubernetes = KubernetesContext {
+            current_context: None,
+            contexts: vec![],
+            namespaces: vec![],
+        };
+        for config in configs {
+            if kubernetes.current_context.is_none() {
+                kubernetes.current_context = config.current_context;
+            }
+            for context in config.contexts {
+                if kubernetes.contexts.iter().any(|c| c.name == context.name) {
+                    continue;
+                }
+                let namespace = context
+                    .context
+                    .namespace
+                    .unwrap_or_else(|| "default".to_string());
+                if !kubernetes.namespaces.contains(&namespace) {
+                    kubernetes.namespaces.push(namespace.clone());
+                }
+                kubernetes.contexts.push(KubeContext {
+                    name: context.name,
+                    cluster: context.context.cluster,
+                    namespace,
+                });
+            }
+        }
+        debug!("found {} kube contexts", kubernetes.contexts.len());
+
+        to_value(kubernetes).ok()
+    }
+}
+
+// the services of the compose file in the cwd, and the running containers when docker is up
+pub struct DockerProvider {
+    settings: ProviderSettings,
+}
+
+impl DockerProvider {
+    pub fn load(config: &ContextConfig) -> DockerProvider {
+        DockerProvider {
+            settings: config.get_settings("docker", 1000, 300),
+        }
+    }
+
+    fn get_services(cwd: &Path) -> Option<(String, Vec<String>)> {
+        let (name, buf) = COMPOSE_FILES.iter().find_map(|name| {
+            let buf = fs::read_to_string(cwd.join(name)).ok()?;
+            Some((name.to_string(), buf))
+        })?;
+
+        let compose = serde_yaml::from_str::<ComposeFile>(&buf)
+            .map_err(|e| warn!("unable to read {}: {}", name, e))
+            .ok()?;
+        let mut services = compose.services.into_keys().collect::<Vec<String>>();
+        services.sort();
+        Some((name, services))
+    }
+
+    // only unix sockets, a tcp DOCKER_HOST is most likely a remote machine
+    #[cfg(unix)]
+    fn get_socket() -> Option<PathBuf> {
+        let socket = match env::var("DOCKER_HOST") {
+            Ok(host) if !host.is_empty() => PathBuf::from(host.strip_prefix("unix://")?),
+            _ => PathBuf::from(DEFAULT_DOCKER_SOCKET),
+        };
+        match socket.exists() {
+            true => Some(socket),
+            false => None,
+        }
+    }
+
+    // docker desktop on windows listens on a named pipe, the running containers are left out there
+    #[cfg(not(unix))]
+    fn get_socket() -> Option<PathBuf> {
+        None
+    }
+
+    // plain http over the socket, 1.0 so the body isn't chunked and the connection closes when
+    // it's done
+    #[cfg(unix)]
+    fn get_containers(socket: &Path, settings: &ProviderSettings) -> Option<Vec<Container>> {
+        let mut stream = UnixStream::connect(socket)
+            .map_err(|e| debug!("docker is not available at {}: {}", socket.display(), e))
+            .ok()?;
+        let timeout = Duration::from_millis(settings.timeout_ms);
+        _ = stream.set_read_timeout(Some(timeout));
+        _ = stream.set_write_timeout(Some(timeout));
+        stream
+            .write_all(b"GET /containers/json HTTP/1.0\r\nHost: docker\r\n\r\n")
+            .ok()?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        let (head, body) = response.split_once("\r\n\r\n")?;
+        if !head.lines().next().unwrap_or_default().contains(" 200 ") {
+            warn!(
+                "docker answered with {}",
+                head.lines().next().unwrap_or_default()
+            );
+            return None;
+        }
+
+        let containers = serde_json::from_str::<Vec<ApiContainer>>(body)
+            .map_err(|e| warn!("unexpected answer from docker: {}", e))
+            .ok()?;
+        Some(
+            containers
+                .into_iter()
+                .map(|container| Container {
+                    name: container
+                        .names
+                        .first()
+                        .map(|name| name.trim_start_matches('/').to_string())
+                        .unwrap_or_default(),
+                    image: container.image,
+                    status: container.status,
+                    pod: container.labels.get(POD_LABEL).cloned(),
+                })
+                .collect(),
+        )
+    }
+
+    #[cfg(not(unix))]
+    fn get_containers(_socket: &Path, _settings: &ProviderSettings) -> Option<Vec<Container>> {
+        None
+    }
+
+    pub fn add_suggestions(suggestions: &mut [ModelSuggestion], ctx: &Context) {
+        let docker = match ctx.get_extra::<DockerContext>("docker") {
+            Some(docker) => docker,
+            None => return,
+        };
+
+        let services = docker
+            .services
+            .iter()
+            .map(|service| MissingFieldSuggestion {
+                value: service.clone(),
+                reasoning: format!(
+                    "service in {}",
+                    docker.compose_file.clone().unwrap_or_default()
+                ),
+            })
+            .collect::<Vec<MissingFieldSuggestion>>();
+        ContextProviders::add_field_suggestions(
+            suggestions,
+            |key| key.contains("service"),
+            &services,
+        );
+
+        let containers = docker
+            .containers
+            .iter()
+            .map(|container| MissingFieldSuggestion {
+                value: container.name.clone(),
+                reasoning: format!("running {}, {}", container.image, container.status),
+            })
+            .collect::<Vec<MissingFieldSuggestion>>();
+        ContextProviders::add_field_suggestions(
+            suggestions,
+            |key| key.contains("container"),
+            &containers,
+        );
+
+        let pods = docker
+            .containers
+            .iter()
+            .filter_map(|container| {
+                Some(MissingFieldSuggestion {
+                    value: container.pod.clone()?,
+                    reasoning: format!("pod running {}", container.image),
+                })
+            })
+            .collect::<Vec<MissingFieldSuggestion>>();
+        ContextProviders::add_field_suggestions(suggestions, |key| key.contains("pod"), &pods);
+    }
+}
+
+impl ContextProvider for DockerProvider {
+    fn name(&self) -> &str {
+        "docker"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, cwd: &Path) -> Option<Value> {
+        let mut docker = DockerContext::default();
+        if let Some((compose_file, services)) = DockerProvider::get_services(cwd) {
+            docker.compose_file = Some(compose_file);
+            docker.services = services;
+        }
+        if let Some(socket) = DockerProvider::get_socket() {
+            docker.containers =
+                DockerProvider::get_containers(&socket, &self.settings).unwrap_or_default();
+        }
+        debug!(
+            "found {} compose services and {} running containers",
+            docker.services.len(),
+            docker.containers.len()
+        );
+
+        match docker.compose_file.is_none() && docker.containers.is_empty() {
+            true => None,
+            false => to_value(docker).ok(),
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{from_value, to_value, Value};
+
+use crate::containers::{DockerProvider, KubernetesProvider};
+use crate::environment::Environment;
+use crate::excerpts::ExcerptProvider;
+use crate::filesystem::FilesystemProvider;
+use crate::index::SimilarProvider;
+use crate::models::*;
+use crate::providers::*;
+use crate::ssh::SshProvider;
+use crate::storage::get_data_dir;
+
+// rough, but close enough to keep each provider to its share of the prompt
+pub const CHARS_PER_TOKEN: usize = 4;
+const EXTERNAL_TIMEOUT_MS: u64 = 2000;
+const EXTERNAL_TOKEN_BUDGET: usize = 500;
+
+#[derive(Deserialize, Clone)]
+pub struct ProviderSettings {
+    pub enabled: bool,
+    pub timeout_ms: u64,
+    // how much of the prompt the provider may take up, anything over it is trimmed
+    pub token_budget: usize,
+}
+
+// what $ZLI_HOME/context.toml can change for a provider, for example
+// [providers.git]
+// enabled = false
+#[derive(Deserialize, Default, Clone)]
+struct SettingsOverride {
+    enabled: Option<bool>,
+    timeout_ms: Option<u64>,
+    token_budget: Option<usize>,
+    // anything else in the table is up to the provider itself
+    #[serde(flatten)]
+    options: toml::Table,
+}
+
+// $ZLI_HOME/context.toml, read once and handed to every provider as it is loaded
+#[derive(Deserialize, Default)]
+pub struct ContextConfig {
+    #[serde(default)]
+    providers: HashMap<String, SettingsOverride>,
+}
+
+impl ContextConfig {
+    pub fn load() -> ContextConfig {
+        let path = get_data_dir().join("context.toml");
+        let buf = match fs::read_to_string(&path) {
+            Ok(buf) => buf,
+            Err(_) => return ContextConfig::default(),
+        };
+
+        toml::from_str::<ContextConfig>(&buf).unwrap_or_else(|e| {
+            warn!("ignoring bad context config {}: {}", path.display(), e);
+            ContextConfig::default()
+        })
+    }
+
+    pub fn get_settings(
+        &self,
+        name: &str,
+        timeout_ms: u64,
+        token_budget: usize,
+    ) -> ProviderSettings {
+        let settings = self.providers.get(name).cloned().unwrap_or_default();
+
+        ProviderSettings {
+            enabled: settings.enabled.unwrap_or(true),
+            timeout_ms: settings.timeout_ms.unwrap_or(timeout_ms),
+            token_budget: settings.token_budget.unwrap_or(token_budget),
+        }
+    }
+
+    // the provider specific settings from its table in the config, the defaults for anything
+    // that isn't there
+    pub fn get_options<T: DeserializeOwned + Default>(&self, name: &str) -> T {
+        let options = match self.providers.get(name) {
+            Some(settings) => settings.options.clone(),
+            None => return T::default(),
+        };
+
+        toml::Value::Table(options)
+            .try_into::<T>()
+            .unwrap_or_else(|e| {
+                warn!("ignoring bad options for context provider {}: {}", name, e);
+                T::default()
+            })
+    }
+}
+
+// a source of context, every provider runs in its own thread and whatever it returns ends up in
+// the context under its name
+pub trait ContextProvider: Send {
+    fn name(&self) -> &str;
+    fn settings(&self) -> &ProviderSettings;
+    // None when there is nothing to add, for example git outside of a repo
+    fn collect(&self, cwd: &Path) -> Option<Value>;
+
+    fn enabled(&self) -> bool {
+        self.settings().enabled
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.settings().timeout_ms)
+    }
+
+    fn token_budget(&self) -> usize {
+        self.settings().token_budget
+    }
+}
+
+// any executable in $ZLI_HOME/providers, it is run in the cwd and has to print json to stdout
+pub struct ExternalProvider {
+    name: String,
+    path: PathBuf,
+    settings: ProviderSettings,
+}
+
+impl ContextProvider for ExternalProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, cwd: &Path) -> Option<Value> {
+        let mut child = Command::new(&self.path)
+            .current_dir(cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| warn!("unable to run context provider {}: {}", self.name, e))
+            .ok()?;
+        let mut stdout = child.stdout.take()?;
+        let reader = thread::spawn(move || {
+            let mut buf = String::new();
+            _ = stdout.read_to_string(&mut buf);
+            buf
+        });
+
+        // the provider is killed once it runs out of time, so nothing is left running behind us
+        let start = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => break,
+                Ok(Some(status)) => {
+                    warn!("context provider {} exited with {}", self.name, status);
+                    return None;
+                }
+                Ok(None) if start.elapsed() < self.timeout() => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                _ => {
+                    _ = child.kill();
+                    _ = child.wait();
+                    return None;
+                }
+            }
+        }
+
+        let output = reader.join().unwrap_or_default();
+        match serde_json::from_str::<Value>(&output) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("context provider {} did not print json: {}", self.name, e);
+                None
+            }
+        }
+    }
+}
+
+pub struct ContextProviders;
+
+impl ContextProviders {
+    pub fn get_external(config: &ContextConfig) -> Vec<Box<dyn ContextProvider>> {
+        let dir = get_data_dir().join("providers");
+        let mut paths = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| ContextProviders::is_executable(path))
+                .collect::<Vec<PathBuf>>(),
+            Err(_) => return vec![],
+        };
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                Box::new(ExternalProvider {
+                    settings: config.get_settings(
+                        &name,
+                        EXTERNAL_TIMEOUT_MS,
+                        EXTERNAL_TOKEN_BUDGET,
+                    ),
+                    name,
+                    path,
+                }) as Box<dyn ContextProvider>
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    fn is_executable(path: &Path) -> bool {
+        fs::metadata(path)
+            .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+
+    // there is no executable bit, any file counts
+    #[cfg(not(unix))]
+    fn is_executable(path: &Path) -> bool {
+        path.is_file()
+    }
+
+    pub fn get_all(history_path: &str, query: &str) -> Vec<Box<dyn ContextProvider>> {
+        let config = ContextConfig::load();
+        let mut providers: Vec<Box<dyn ContextProvider>> = vec![
+            Box::new(FilesystemProvider::load(&config)),
+            Box::new(HistoryProvider::load(&config, history_path)),
+            Box::new(GitProvider::load(&config)),
+            Box::new(ProjectProvider::load(&config)),
+            Box::new(EnvironmentProvider::load(&config)),
+            Box::new(SshProvider::load(&config)),
+            Box::new(KubernetesProvider::load(&config)),
+            Box::new(DockerProvider::load(&config)),
+            Box::new(ExcerptProvider::load(&config, query)),
+            Box::new(SimilarProvider::load(&config, query)),
+        ];
+        providers.extend(ContextProviders::get_external(&config));
+        providers
+    }
+
+    // every enabled provider runs at the same time, the ones that don't make it in time are left
+    // out of the context
+    pub fn collect(providers: Vec<Box<dyn ContextProvider>>, cwd: &str) -> Context {
+        let mut ctx = Context::new(cwd);
+        let (sender, receiver) = mpsc::channel();
+        let mut pending: HashMap<String, Instant> = HashMap::new();
+
+        for provider in providers.into_iter().filter(|provider| provider.enabled()) {
+            let name = provider.name().to_string();
+            if pending.contains_key(&name) || ctx.budgets.contains_key(&name) {
+                warn!("there is more than one context provider named {}", name);
+                continue;
+            }
+            pending.insert(name.clone(), Instant::now() + provider.timeout());
+            ctx.budgets.insert(name.clone(), provider.token_budget());
+
+            let sender = sender.clone();
+            let cwd = PathBuf::from(cwd);
+            thread::spawn(move || {
+                let start = Instant::now();
+                let value = provider.collect(&cwd);
+                debug!("context provider {} took {:?}", name, start.elapsed());
+                _ = sender.send((name, value));
+            });
+        }
+        drop(sender);
+
+        while let Some(deadline) = pending.values().min().copied() {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok((name, value)) => {
+                    pending.remove(&name);
+                    if let Some(value) = value {
+                        ctx.insert(&name, value);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let now = Instant::now();
+                    pending.retain(|name, deadline| {
+                        if *deadline <= now {
+                            warn!("context provider {} timed out", name);
+                        }
+                        *deadline > now
+                    });
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        ctx
+    }
+
+    // offers values the context knows about for the missing fields is_field picks out, after
+    // whatever the model or the history already came up with
+    pub fn add_field_suggestions(
+        suggestions: &mut [ModelSuggestion],
+        is_field: impl Fn(&str) -> bool,
+        values: &[MissingFieldSuggestion],
+    ) {
+        let fields = suggestions
+            .iter_mut()
+            .flat_map(|suggestion| suggestion.commands.iter_mut())
+            .flat_map(|command| command.missing_fields.iter_mut())
+            .filter(|field| is_field(&field.key.to_lowercase()));
+        for field in fields {
+            for value in values {
+                if !field
+                    .suggestions
+                    .iter()
+                    .any(|suggestion| suggestion.value == value.value)
+                {
+                    field.suggestions.push(value.clone());
+                }
+            }
+        }
+    }
+
+    pub fn get_chars(value: &Value) -> usize {
+        value.to_string().len()
+    }
+
+    // arrays keep their last entries, for the history those are the most recent ones. objects have
+    // their biggest array trimmed until they fit, and anything else that is too big is dropped
+    pub fn fit(value: Value, token_budget: usize) -> Value {
+        let max_chars = token_budget * CHARS_PER_TOKEN;
+        if ContextProviders::get_chars(&value) <= max_chars {
+            return value;
+        }
+
+        match value {
+            Value::Array(items) => {
+                let mut chars = 2;
+                let mut kept = items
+                    .into_iter()
+                    .rev()
+                    .take_while(|item| {
+                        chars += ContextProviders::get_chars(item) + 1;
+                        chars <= max_chars
+                    })
+                    .collect::<Vec<Value>>();
+                kept.reverse();
+                Value::Array(kept)
+            }
+            Value::Object(mut fields) => {
+                while ContextProviders::get_chars(&Value::Object(fields.clone())) > max_chars {
+                    let biggest = fields
+                        .iter()
+                        .filter(|(_, field)| {
+                            field.as_array().is_some_and(|items| !items.is_empty())
+                        })
+                        .max_by_key(|(_, field)| ContextProviders::get_chars(field))
+                        .map(|(key, _)| key.clone());
+                    match biggest {
+                        Some(key) => {
+                            if let Some(Value::Array(items)) = fields.get_mut(&key) {
+                                items.remove(0);
+                            }
+                        }
+                        None => return Value::Null,
+                    }
+                }
+                Value::Object(fields)
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Context {
+    pub fn new(cwd: &str) -> Context {
+        Context {
+            cwd: cwd.to_string(),
+            ls: vec![],
+            history: vec![],
+            environment: Environment::default(),
+            extra: BTreeMap::new(),
+            budgets: HashMap::new(),
+        }
+    }
+
+    // the built in providers fill in the fields the rest of the code works with, anything else is
+    // kept as is
+    pub fn insert(&mut self, name: &str, value: Value) {
+        let result = match name {
+            "filesystem" => from_value(value).map(|ls| self.ls = ls),
+            "history" => from_value(value).map(|history| self.history = history),
+            "environment" => from_value(value).map(|environment| self.environment = environment),
+            _ => {
+                self.extra.insert(name.to_string(), value);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            warn!(
+                "context provider {} returned something unexpected: {}",
+                name, e
+            );
+        }
+    }
+
+    // what one of the other providers came up with, None when it had nothing or was turned off
+    pub fn get_extra<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let value = self.extra.get(name)?;
+        from_value(value.clone())
+            .map_err(|e| warn!("unexpected context from {}: {}", name, e))
+            .ok()
+    }
+
+    // what goes into the prompt, every provider trimmed down to its token budget
+    pub fn get_prompt_value(&self) -> Value {
+        let mut value = to_value(self).unwrap_or_else(|e| panic!("{}", e));
+        if let Value::Object(fields) = &mut value {
+            for (name, budget) in &self.budgets {
+                if let Some(field) = fields.remove(name) {
+                    let field = ContextProviders::fit(field, *budget);
+                    if !field.is_null() {
+                        fields.insert(name.clone(), field);
+                    } else {
+                        warn!("context from {} does not fit in its token budget", name);
+                    }
+                }
+            }
+        }
+        value
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::verify::Verify;
+
+// package manager binary, the distro ids it is the default for and how to install with it
+const PACKAGE_MANAGERS: [(&str, &[&str], &str); 7] = [
+    ("brew", &["macos"], "brew install"),
+    ("apt-get", &["debian", "ubuntu"], "sudo apt-get install"),
+    ("dnf", &["fedora", "rhel", "centos"], "sudo dnf install"),
+    ("yum", &["rhel", "centos", "amzn"], "sudo yum install"),
+    ("pacman", &["arch"], "sudo pacman -S"),
+    ("apk", &["alpine"], "sudo apk add"),
+    (
+        "zypper",
+        &["opensuse", "suse", "sles"],
+        "sudo zypper install",
+    ),
+];
+
+// what the commands have to run on, the prompts use it to pick the right flags and tools
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Environment {
+    pub os: String,
+    pub distro: Option<String>,
+    pub package_manager: Option<String>,
+    pub shell: String,
+    // gnu, bsd or busybox, decides things like `sed -i` vs `sed -i ''`
+    pub coreutils: String,
+    pub sed: String,
+}
+
+// used when the environment provider is turned off, nothing is assumed beyond the os
+impl Default for Environment {
+    fn default() -> Environment {
+        Environment {
+            os: env::consts::OS.to_string(),
+            distro: None,
+            package_manager: None,
+            shell: "sh".to_string(),
+            coreutils: "unknown".to_string(),
+            sed: "unknown".to_string(),
+        }
+    }
+}
+
+impl Environment {
+    pub fn detect() -> Environment {
+        let os = match env::consts::OS {
+            "macos" => "macos".to_string(),
+            os => os.to_string(),
+        };
+        let os_release = Environment::get_os_release();
+        let distro = match os.as_str() {
+            "macos" => Environment::get_output("sw_vers", "-productVersion")
+                .map(|version| format!("macOS {}", version.trim())),
+            _ => os_release
+                .get("PRETTY_NAME")
+                .or_else(|| os_release.get("NAME"))
+                .cloned(),
+        };
+
+        let mut ids = vec![os.clone()];
+        for key in ["ID", "ID_LIKE"] {
+            if let Some(value) = os_release.get(key) {
+                ids.extend(value.split_whitespace().map(|id| id.to_string()));
+            }
+        }
+
+        let environment = Environment {
+            package_manager: Environment::detect_package_manager(&ids),
+            shell: env::var("SHELL")
+                .ok()
+                .and_then(|shell| {
+                    Path::new(&shell)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                })
+                .unwrap_or_else(|| "sh".to_string()),
+            coreutils: Environment::get_variant("ls", &os),
+            sed: Environment::get_variant("sed", &os),
+            os,
+            distro,
+        };
+        debug!(
+            "environment is {}",
+            serde_json::to_string(&environment).unwrap_or_default()
+        );
+        environment
+    }
+
+    // KEY=value lines, values may be quoted
+    fn get_os_release() -> HashMap<String, String> {
+        let buf = fs::read_to_string("/etc/os-release")
+            .or_else(|_| fs::read_to_string("/usr/lib/os-release"))
+            .unwrap_or_default();
+
+        buf.lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| {
+                (
+                    key.trim().to_string(),
+                    value
+                        .trim()
+                        .trim_matches('"')
+                        .trim_matches('\'')
+                        .to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn get_output(program: &str, arg: &str) -> Option<String> {
+        let output = Command::new(program)
+            .arg(arg)
+            .stdin(Stdio::null())
+            .output()
+            .ok()?;
+        Some(format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+
+    // the one the distro ships with when it's installed, otherwise whatever is around
+    fn detect_package_manager(ids: &[String]) -> Option<String> {
+        let installed = PACKAGE_MANAGERS
+            .iter()
+            .filter(|(manager, _, _)| Verify::binary_exists(manager))
+            .collect::<Vec<_>>();
+
+        installed
+            .iter()
+            .find(|(_, distros, _)| {
+                distros
+                    .iter()
+                    .any(|distro| ids.iter().any(|id| id == distro))
+            })
+            .or_else(|| installed.first())
+            .map(|(manager, _, _)| manager.to_string())
+    }
+
+    pub fn get_install_command(package_manager: &str) -> Option<&'static str> {
+        PACKAGE_MANAGERS
+            .iter()
+            .find(|(manager, _, _)| *manager == package_manager)
+            .map(|(_, _, install)| *install)
+    }
+
+    // only the gnu and busybox tools know --version, the bsd ones complain about it
+    fn get_variant(program: &str, os: &str) -> String {
+        let version = Environment::get_output(program, "--version").unwrap_or_default();
+        if version.contains("GNU") {
+            "gnu".to_string()
+        } else if version.contains("BusyBox") {
+            "busybox".to_string()
+        } else if os == "macos" || os.ends_with("bsd") {
+            "bsd".to_string()
+        } else {
+            "unknown".to_string()
+        }
+    }
+
+    // a sentence for the prompts
+    pub fn get_description(&self) -> String {
+        let mut description = format!(
+            "The user is on {}",
+            self.distro.clone().unwrap_or_else(|| self.os.clone())
+        );
+        description.push_str(&format!(", uses the {} shell", self.shell));
+        description.push_str(&format!(
+            ", has {} coreutils and {} sed",
+            self.coreutils, self.sed
+        ));
+        match &self.package_manager {
+            Some(manager) => description.push_str(&format!(
+                " and installs packages with `{} <package>`.",
+                Environment::get_install_command(manager).unwrap_or(manager)
+            )),
+            None => description.push_str(" and has no known package manager."),
+        }
+        description.push_str(
+            " Only use flags and syntax that work with these exact variants, for example \
+             `sed -i ''` on bsd sed and `sed -i` on gnu sed.",
+        );
+        description
+    }
+}
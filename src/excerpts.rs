@@ -0,0 +1,217 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{to_value, Value};
+
+use crate::context::{
+    ContextConfig, ContextProvider, ContextProviders, ProviderSettings, CHARS_PER_TOKEN,
+};
+
+// no more than this is read from any one file
+const MAX_READ_BYTES: u64 = 8192;
+const MAX_EXCERPT_FILES: usize = 5;
+// an excerpt smaller than this isn't worth the space it takes up
+const MIN_EXCERPT_CHARS: usize = 200;
+
+// files that say what can be run or built, with the words in a query that point at them
+const KNOWN_MANIFESTS: [(&str, &[&str]); 14] = [
+    (
+        "package.json",
+        &["npm", "node", "script", "yarn", "pnpm", "dev"],
+    ),
+    ("Makefile", &["make", "target", "build"]),
+    ("makefile", &["make", "target", "build"]),
+    ("justfile", &["just", "recipe"]),
+    ("Taskfile.yml", &["task"]),
+    ("Dockerfile", &["docker", "image", "container", "tag"]),
+    ("compose.yaml", &["compose", "service", "docker"]),
+    ("docker-compose.yml", &["compose", "service", "docker"]),
+    ("Cargo.toml", &["cargo", "crate", "feature", "rust"]),
+    ("pyproject.toml", &["python", "poetry", "pip", "script"]),
+    ("requirements.txt", &["python", "pip", "install"]),
+    ("go.mod", &["go", "module"]),
+    ("Gemfile", &["ruby", "bundle", "gem"]),
+    ("Procfile", &["process", "worker", "web"]),
+];
+
+// never read, whatever is in them is a secret and redaction would only get part of it. names
+// are matched by how they start, so .env.local, id_ed25519.pub and credentials.json are in too
+const SENSITIVE_NAMES: [&str; 6] = [".env", ".netrc", ".npmrc", ".pypirc", "id_", "credentials"];
+const SENSITIVE_EXTENSIONS: [&str; 5] = ["pem", "key", "p12", "pfx", "kdbx"];
+
+#[derive(Serialize, Deserialize)]
+pub struct Excerpt {
+    pub path: String,
+    pub content: String,
+    // when the file goes on after the excerpt
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+// the start of the files the query mentions and of the manifests in the cwd, so the model knows
+// the scripts, targets and tags that are there
+pub struct ExcerptProvider {
+    query: String,
+    settings: ProviderSettings,
+}
+
+impl ExcerptProvider {
+    pub fn load(config: &ContextConfig, query: &str) -> ExcerptProvider {
+        ExcerptProvider {
+            query: query.to_string(),
+            settings: config.get_settings("files", 1000, 800),
+        }
+    }
+
+    // files named in the query come first, then the manifests the query hints at, then the rest
+    fn get_candidates(&self, cwd: &Path) -> Vec<PathBuf> {
+        let query = self.query.to_lowercase();
+        let words = query
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c: char| "'\"`,;:!?()".contains(c)))
+            .collect::<Vec<&str>>();
+
+        // a symlink, or a file in a symlinked dir, can point anywhere, like ~/.aws/credentials
+        let real_cwd = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+        let mut candidates: Vec<PathBuf> = vec![];
+        let mut push = |path: PathBuf| {
+            let is_file = fs::symlink_metadata(&path)
+                .map(|meta| meta.file_type().is_file())
+                .unwrap_or(false);
+            let is_inside = path
+                .canonicalize()
+                .map(|real| real.starts_with(&real_cwd))
+                .unwrap_or(false);
+            if is_file && is_inside && !candidates.contains(&path) {
+                candidates.push(path);
+            }
+        };
+
+        // only what's under the cwd, a query about /etc/shadow doesn't get it sent along
+        for word in self.query.split_whitespace() {
+            let word = Path::new(word.trim_matches(|c: char| "'\"`,;:!?()".contains(c)));
+            if word.is_relative() && word.components().all(|c| matches!(c, Component::Normal(_))) {
+                push(cwd.join(word));
+            }
+        }
+        for (name, keywords) in KNOWN_MANIFESTS {
+            if words.contains(&name.to_lowercase().as_str())
+                || keywords.iter().any(|keyword| words.contains(keyword))
+            {
+                push(cwd.join(name));
+            }
+        }
+        for (name, _) in KNOWN_MANIFESTS {
+            push(cwd.join(name));
+        }
+
+        candidates
+            .into_iter()
+            .filter(|path| !ExcerptProvider::is_sensitive(path))
+            .collect()
+    }
+
+    fn is_sensitive(path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        SENSITIVE_NAMES
+            .iter()
+            .any(|sensitive| name.starts_with(sensitive))
+            || SENSITIVE_EXTENSIONS.contains(&extension.as_str())
+    }
+
+    // whole lines from the start of the file up to max_chars, None for binary files
+    fn read_excerpt(path: &Path, max_chars: usize) -> Option<(String, bool)> {
+        let file = fs::File::open(path).ok()?;
+        let size = file.metadata().ok()?.len();
+        let mut buf = vec![];
+        file.take(MAX_READ_BYTES).read_to_end(&mut buf).ok()?;
+        if buf.contains(&0) {
+            return None;
+        }
+
+        // the read can stop in the middle of a character, anything else that isn't utf-8 is binary
+        let text = match std::str::from_utf8(&buf) {
+            Ok(text) => text,
+            Err(e) if e.error_len().is_none() => {
+                std::str::from_utf8(&buf[..e.valid_up_to()]).ok()?
+            }
+            Err(_) => return None,
+        };
+
+        let mut excerpt = String::new();
+        for line in text.split_inclusive('\n') {
+            if excerpt.len() + line.len() > max_chars {
+                break;
+            }
+            excerpt.push_str(line);
+        }
+        // a minified file is one long line, some of it is better than none
+        if excerpt.is_empty() {
+            excerpt = text.chars().take(max_chars).collect();
+        }
+        let truncated = excerpt.len() as u64 != size;
+        Some((excerpt, truncated))
+    }
+}
+
+impl ContextProvider for ExcerptProvider {
+    fn name(&self) -> &str {
+        "files"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    // the budget is spent here in order of priority, so trimming never drops the file the query
+    // is about in favour of one it isn't
+    fn collect(&self, cwd: &Path) -> Option<Value> {
+        let mut remaining = self.settings.token_budget * CHARS_PER_TOKEN;
+        let mut excerpts: Vec<Excerpt> = vec![];
+        for path in self.get_candidates(cwd) {
+            if excerpts.len() >= MAX_EXCERPT_FILES || remaining < MIN_EXCERPT_CHARS {
+                break;
+            }
+            let name = path
+                .strip_prefix(cwd)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string();
+            // room for the path, the field names and the escaping of the content
+            let max_chars = remaining.saturating_sub(name.len() + 50) * 4 / 5;
+            let (content, truncated) = match ExcerptProvider::read_excerpt(&path, max_chars) {
+                Some(excerpt) if !excerpt.0.trim().is_empty() => excerpt,
+                _ => {
+                    debug!("no excerpt from {}", path.display());
+                    continue;
+                }
+            };
+
+            let excerpt = Excerpt {
+                path: name,
+                content,
+                truncated,
+            };
+            remaining = remaining
+                .saturating_sub(ContextProviders::get_chars(&to_value(&excerpt).ok()?) + 1);
+            excerpts.push(excerpt);
+        }
+        debug!("added excerpts of {} files", excerpts.len());
+
+        match excerpts.is_empty() {
+            true => None,
+            false => to_value(excerpts).ok(),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::models::*;
+
+pub struct Explain;
+
+impl Explain {
+    // splits a command on |, ||, && and ; as long as they are not quoted, every part is a stage
+    // which gets explained on its own
+    pub fn split_stages(cmd: &str) -> Vec<String> {
+        let mut stages = vec![];
+        let mut current = String::new();
+        let mut quote: Option<char> = None;
+        let mut chars = cmd.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(q), c) if c == q => {
+                    quote = None;
+                    current.push(c);
+                }
+                (Some(_), '\\') | (None, '\\') => {
+                    current.push(c);
+                    if let Some(next) = chars.next() {
+                        current.push(next);
+                    }
+                }
+                (Some(_), c) => current.push(c),
+                (None, '\'') | (None, '"') => {
+                    quote = Some(c);
+                    current.push(c);
+                }
+                (None, '|') | (None, '&') | (None, ';') => {
+                    // a single & puts a job in the background and 2>&1 is a redirect, neither
+                    // of those starts a new stage
+                    let doubled = chars.peek() == Some(&c);
+                    if c == '&' && (!doubled || current.ends_with('>')) {
+                        current.push(c);
+                        continue;
+                    }
+                    if doubled {
+                        chars.next();
+                    }
+                    stages.push(current.trim().to_string());
+                    current = String::new();
+                }
+                (None, c) => current.push(c),
+            }
+        }
+        stages.push(current.trim().to_string());
+
+        stages
+            .into_iter()
+            .filter(|stage| !stage.is_empty())
+            .collect()
+    }
+
+    pub fn get_user_message(cmd: &str) -> String {
+        let stages = Explain::split_stages(cmd)
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| format!("stage {} -> {}", i + 1, stage))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        format!("command -> {}\n{}", cmd, stages)
+    }
+
+    pub fn print_explanation(explanation: &ExplainResponse) {
+        if explanation.stages.is_empty() {
+            println!("no explanation");
+            return;
+        }
+
+        for (i, stage) in explanation.stages.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("stage {} - {}", i + 1, stage.command);
+            println!("  {}", stage.summary);
+
+            let width = stage
+                .tokens
+                .iter()
+                .map(|token| token.token.chars().count())
+                .max()
+                .unwrap_or_default();
+            for token in &stage.tokens {
+                println!(
+                    "    {:<width$}  {}",
+                    token.token,
+                    token.explanation,
+                    width = width
+                );
+            }
+
+            if !stage.side_effects.is_empty() {
+                println!("  side effects:");
+                for side_effect in &stage.side_effects {
+                    println!("    - {}", side_effect);
+                }
+            }
+            println!(
+                "  risk: {} - {}",
+                stage.risk.to_uppercase(),
+                stage.risk_reasoning
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::Local;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::history_search::HistorySearch;
+use crate::models::*;
+use crate::storage::get_data_dir;
+
+const MAX_EXAMPLES: usize = 3;
+const MIN_SCORE: f64 = 0.5;
+// values typed before that the model didn't suggest, only the most used few are offered
+const MAX_PAST_VALUES: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FieldValue {
+    pub key: String,
+    pub value: String,
+}
+
+// one line in the feedback file, what was shown for a query and what the user did with it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Choice {
+    pub datetime: String,
+    pub query: String,
+    pub cwd: String,
+    pub picked: Option<ModelSuggestion>,
+    pub skipped: Vec<String>,
+    pub values: Vec<FieldValue>,
+}
+
+impl Choice {
+    pub fn new(
+        query: &str,
+        suggestions: &[ModelSuggestion],
+        picked: Option<usize>,
+        values: Vec<FieldValue>,
+    ) -> Choice {
+        Choice {
+            datetime: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            query: query.to_string(),
+            cwd: env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            picked: picked.map(|i| suggestions[i].clone()),
+            skipped: suggestions
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| Some(*i) != picked)
+                .map(|(_, suggestion)| suggestion.reasoning.clone())
+                .collect(),
+            values,
+        }
+    }
+}
+
+pub struct Feedback;
+
+impl Feedback {
+    fn get_feedback_file_path() -> PathBuf {
+        get_data_dir().join("feedback.jsonl")
+    }
+
+    pub fn save(choice: &Choice) {
+        let path = Feedback::get_feedback_file_path();
+        debug!("saving choice to {}", path.display());
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|e| panic!("{}", e));
+        let line = serde_json::to_string(choice).unwrap_or_else(|e| panic!("{}", e));
+        writeln!(file, "{}", line).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    pub fn load() -> Vec<Choice> {
+        let buf = fs::read_to_string(Feedback::get_feedback_file_path()).unwrap_or_default();
+
+        buf.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<Choice>(line) {
+                Ok(choice) => Some(choice),
+                Err(e) => {
+                    warn!("skipping bad line in feedback file: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // past picks for queries that read like this one, the ones made in the same dir count a bit more
+    pub fn get_examples(query: &str, cwd: &str, choices: &[Choice]) -> Vec<Choice> {
+        let query_words = query
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<String>>();
+        if query_words.is_empty() {
+            return vec![];
+        }
+
+        let mut matches = choices
+            .iter()
+            .filter(|choice| choice.picked.is_some())
+            .map(|choice| {
+                let score = HistorySearch::get_text_score(&query_words, &choice.query);
+                let weight = match choice.cwd == cwd {
+                    true => 1.2,
+                    false => 1.0,
+                };
+                (score * weight, choice)
+            })
+            .filter(|(score, _)| *score >= MIN_SCORE)
+            .collect::<Vec<(f64, &Choice)>>();
+        // the newest pick wins between equally good matches
+        matches.reverse();
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut examples: Vec<Choice> = vec![];
+        for (_, choice) in matches {
+            if examples.iter().any(|example| example.query == choice.query) {
+                continue;
+            }
+            examples.push(choice.clone());
+            if examples.len() == MAX_EXAMPLES {
+                break;
+            }
+        }
+        examples
+    }
+
+    // suggestions the user went with before move to the top, and values they typed themselves are
+    // offered as suggestions too
+    pub fn rank_field(field: &MissingField, choices: &[Choice]) -> MissingField {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for value in choices
+            .iter()
+            .flat_map(|choice| &choice.values)
+            .filter(|value| value.key == field.key)
+        {
+            *counts.entry(value.value.as_str()).or_default() += 1;
+        }
+
+        let mut field = field.clone();
+        field
+            .suggestions
+            .sort_by_key(|suggestion| std::cmp::Reverse(counts.get(suggestion.value.as_str())));
+
+        let mut past_values = counts
+            .iter()
+            .filter(|(value, _)| {
+                !value.is_empty() && !field.suggestions.iter().any(|s| s.value == **value)
+            })
+            .collect::<Vec<(&&str, &usize)>>();
+        past_values.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (value, count) in past_values.into_iter().take(MAX_PAST_VALUES) {
+            field.suggestions.push(MissingFieldSuggestion {
+                value: value.to_string(),
+                reasoning: format!("you used this {} time(s) before", count),
+            });
+        }
+
+        field
+    }
+}
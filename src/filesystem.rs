@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use ignore::{DirEntry, WalkBuilder};
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::{to_value, Value};
+
+use crate::context::{ContextConfig, ContextProvider, ProviderSettings};
+use crate::models::*;
+
+// the walk stops here no matter the depth, so a huge tree can't hold up the prompt
+const MAX_WALKED_ENTRIES: usize = 10000;
+
+// what [providers.filesystem] in context.toml can set on top of the usual settings
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WalkOptions {
+    // 1 is only the cwd itself
+    pub max_depth: usize,
+    pub max_entries: usize,
+    // directories with more entries than this are summarized instead of listed
+    pub summarize_over: usize,
+    pub gitignore: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> WalkOptions {
+        WalkOptions {
+            max_depth: 2,
+            max_entries: 100,
+            summarize_over: 20,
+            gitignore: true,
+        }
+    }
+}
+
+// the cwd and what's below it, leaving out whatever git ignores
+pub struct FilesystemProvider {
+    settings: ProviderSettings,
+    options: WalkOptions,
+}
+
+impl FilesystemProvider {
+    pub fn load(config: &ContextConfig) -> FilesystemProvider {
+        FilesystemProvider {
+            settings: config.get_settings("filesystem", 1000, 500),
+            options: config.get_options("filesystem"),
+        }
+    }
+
+    // every entry grouped by the directory it is in, in name order
+    fn walk(&self, cwd: &Path) -> HashMap<PathBuf, Vec<DirEntry>> {
+        let mut entries: HashMap<PathBuf, Vec<DirEntry>> = HashMap::new();
+        let walker = WalkBuilder::new(cwd)
+            .max_depth(Some(self.options.max_depth))
+            .hidden(false)
+            .parents(self.options.gitignore)
+            .ignore(self.options.gitignore)
+            .git_ignore(self.options.gitignore)
+            .git_global(self.options.gitignore)
+            .git_exclude(self.options.gitignore)
+            .require_git(false)
+            .follow_links(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+
+        for entry in walker
+            .filter_map(|entry| {
+                entry
+                    .map_err(|e| debug!("skipping while listing {}: {}", cwd.display(), e))
+                    .ok()
+            })
+            .filter(|entry| entry.depth() > 0)
+            .take(MAX_WALKED_ENTRIES)
+        {
+            let parent = entry.path().parent().unwrap_or(cwd).to_path_buf();
+            entries.entry(parent).or_default().push(entry);
+        }
+        entries
+    }
+
+    fn list(
+        &self,
+        cwd: &Path,
+        dir: &Path,
+        entries: &HashMap<PathBuf, Vec<DirEntry>>,
+        ls: &mut Vec<File>,
+    ) {
+        for entry in entries.get(dir).map(|e| e.as_slice()).unwrap_or_default() {
+            if ls.len() >= self.options.max_entries {
+                return;
+            }
+
+            let mut file = FilesystemProvider::get_file(cwd, entry);
+            let children = entries
+                .get(entry.path())
+                .map(|e| e.as_slice())
+                .unwrap_or_default();
+            if file.kind != "directory" || children.len() <= self.options.summarize_over {
+                ls.push(file);
+                self.list(cwd, entry.path(), entries, ls);
+                continue;
+            }
+
+            file.summary = Some(FilesystemProvider::summarize(children));
+            ls.push(file);
+        }
+    }
+
+    fn get_file(cwd: &Path, entry: &DirEntry) -> File {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let metadata = entry.path().symlink_metadata().ok();
+        let kind = match entry.file_type() {
+            Some(kind) if kind.is_symlink() => "symlink",
+            Some(kind) if kind.is_dir() => "directory",
+            Some(kind) if kind.is_file() => "file",
+            _ => "other",
+        };
+
+        File {
+            name: entry
+                .path()
+                .strip_prefix(cwd)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .to_string(),
+            kind: kind.to_string(),
+            size: metadata
+                .as_ref()
+                .filter(|_| kind == "file")
+                .map(|metadata| metadata.len()),
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok())
+                .map(|time| {
+                    DateTime::<Local>::from(time)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                }),
+            extension: match kind {
+                "file" => FilesystemProvider::get_extension(&name),
+                _ => None,
+            },
+            target: match kind {
+                "symlink" => fs::read_link(entry.path())
+                    .ok()
+                    .map(|target| target.to_string_lossy().to_string()),
+                _ => None,
+            },
+            executable: kind == "file"
+                && metadata
+                    .as_ref()
+                    .map(FilesystemProvider::is_executable)
+                    .unwrap_or(false),
+            hidden: name.starts_with('.'),
+            summary: None,
+        }
+    }
+
+    #[cfg(unix)]
+    fn is_executable(metadata: &fs::Metadata) -> bool {
+        metadata.permissions().mode() & 0o111 != 0
+    }
+
+    // there is no executable bit to go by
+    #[cfg(not(unix))]
+    fn is_executable(_metadata: &fs::Metadata) -> bool {
+        false
+    }
+
+    // .bashrc is a name, not an extension
+    fn get_extension(name: &str) -> Option<String> {
+        match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() && !extension.is_empty() => {
+                Some(extension.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    // "412 .json files, 3 directories", the most common kind of entry first
+    fn summarize(entries: &[DirEntry]) -> String {
+        let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let group = match entry.file_type() {
+                Some(kind) if kind.is_dir() => ("directory".to_string(), "directories".to_string()),
+                Some(kind) if kind.is_symlink() => ("symlink".to_string(), "symlinks".to_string()),
+                _ => match FilesystemProvider::get_extension(&name) {
+                    Some(extension) => (
+                        format!(".{} file", extension),
+                        format!(".{} files", extension),
+                    ),
+                    None => ("other file".to_string(), "other files".to_string()),
+                },
+            };
+            *counts.entry(group).or_default() += 1;
+        }
+
+        let mut counts = counts
+            .into_iter()
+            .collect::<Vec<((String, String), usize)>>();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts
+            .iter()
+            .map(|((one, many), count)| match count {
+                1 => format!("1 {}", one),
+                _ => format!("{} {}", count, many),
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+impl ContextProvider for FilesystemProvider {
+    fn name(&self) -> &str {
+        "filesystem"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, cwd: &Path) -> Option<Value> {
+        if let Err(e) = cwd.read_dir() {
+            warn!("unable to list {}: {}", cwd.display(), e);
+            return None;
+        }
+
+        let entries = self.walk(cwd);
+        let mut ls = vec![];
+        self.list(cwd, cwd, &entries, &mut ls);
+
+        // whatever didn't fit is at least counted
+        let total = entries.values().map(|e| e.len()).sum::<usize>();
+        let listed = ls.len()
+            + ls.iter()
+                .filter(|file| file.summary.is_some())
+                .map(|file| entries.get(&cwd.join(&file.name)).map_or(0, |e| e.len()))
+                .sum::<usize>();
+        debug!("listed {} of {} entries under the cwd", ls.len(), total);
+        if ls.len() >= self.options.max_entries && total > listed {
+            ls.push(File {
+                name: ".".to_string(),
+                kind: "summary".to_string(),
+                summary: Some(format!("{} more entries not listed", total - listed)),
+                ..File::default()
+            });
+        }
+
+        to_value(ls).ok()
+    }
+}
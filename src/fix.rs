@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use log::debug;
+
+use crate::models::*;
+use crate::storage::get_data_dir;
+
+// only the tail of the error output is sent, that's where the actual error usually is
+const MAX_STDERR_CHARS: usize = 4000;
+
+pub struct LastCommand {
+    pub cmd: String,
+    pub dir: String,
+    pub exit_status: Option<i32>,
+    pub stderr: Option<String>,
+}
+
+impl LastCommand {
+    // written by the shell hook after every command, see Hook::get_script
+    pub fn from_hook() -> Option<LastCommand> {
+        let buf = fs::read_to_string(get_data_dir().join("last_command")).ok()?;
+        let mut lines = buf.lines();
+
+        let exit_status = lines.next()?.trim().parse::<i32>().ok();
+        let dir = lines.next()?.to_string();
+        let cmd = lines.collect::<Vec<&str>>().join("\n");
+        if cmd.trim().is_empty() {
+            return None;
+        }
+        debug!("last command from the shell hook is {}", cmd);
+
+        Some(LastCommand {
+            cmd,
+            dir,
+            exit_status,
+            stderr: None,
+        })
+    }
+
+    pub fn from_history(ctx: &Context) -> Option<LastCommand> {
+        let last = ctx.history.last()?;
+        debug!("last command from the history is {}", last.cmd);
+
+        Some(LastCommand {
+            cmd: last.cmd.clone(),
+            dir: last.dir.clone(),
+            exit_status: None,
+            stderr: None,
+        })
+    }
+
+    // runs the command again in the dir it was run in to get hold of its exit status and stderr
+    pub fn rerun(&mut self) {
+        let dir = match Path::new(&self.dir).is_dir() {
+            true => self.dir.clone(),
+            false => ".".to_string(),
+        };
+        debug!("re-running {} in {}", self.cmd, dir);
+
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.cmd)
+            .current_dir(dir)
+            .output()
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        self.exit_status = output.status.code();
+        self.stderr = Some(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+
+    pub fn get_user_message(&self) -> String {
+        let exit_status = match self.exit_status {
+            Some(code) => code.to_string(),
+            None => "unknown".to_string(),
+        };
+
+        let stderr = self.stderr.clone().unwrap_or_default();
+        let stderr_chars = stderr.chars().count();
+        let stderr = match stderr_chars > MAX_STDERR_CHARS {
+            true => stderr
+                .chars()
+                .skip(stderr_chars - MAX_STDERR_CHARS)
+                .collect(),
+            false => stderr,
+        };
+
+        format!(
+            "The following command failed, suggest corrected commands that do what it was meant to do.\n\
+             command -> {}\ndirectory -> {}\nexit status -> {}\nerror output ->\n{}",
+            self.cmd,
+            self.dir,
+            exit_status,
+            stderr.trim_end()
+        )
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use log::{debug, trace};
+
+use crate::models::*;
+
+const MAX_SUGGESTIONS: usize = 5;
+// matches scoring below this are too far off to be worth showing
+const MIN_SCORE: f64 = 0.3;
+
+struct Match<'a> {
+    entry: &'a History,
+    score: f64,
+    count: usize,
+}
+
+// offline stand in for the model, fuzzy matches the query against the users history
+pub struct HistorySearch;
+
+impl HistorySearch {
+    pub fn get_suggestions(query: &str, ctx: &Context) -> Vec<ModelSuggestion> {
+        let query_words = query
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<String>>();
+        if query_words.is_empty() {
+            return vec![];
+        }
+
+        let now = Local::now().naive_local();
+        let total = ctx.history.len();
+
+        // the same command run many times only shows up once, with its best score
+        let mut matches: HashMap<String, Match> = HashMap::new();
+        for (i, entry) in ctx.history.iter().enumerate() {
+            let text_score = HistorySearch::get_text_score(&query_words, &entry.cmd);
+            if text_score == 0.0 {
+                continue;
+            }
+
+            let score = text_score
+                * HistorySearch::get_dir_weight(&ctx.cwd, &entry.dir)
+                * HistorySearch::get_recency_weight(entry, i, total, now);
+            trace!("history match {} scored {}", entry.cmd, score);
+
+            let (template, _) = HistorySearch::get_template(&entry.cmd);
+            let m = matches.entry(template).or_insert(Match {
+                entry,
+                score,
+                count: 0,
+            });
+            m.count += 1;
+            if score > m.score {
+                m.entry = entry;
+                m.score = score;
+            }
+        }
+
+        let mut matches = matches
+            .into_values()
+            .map(|mut m| {
+                // commands the user keeps coming back to get a small bump
+                m.score *= 1.0 + (m.count as f64).ln() * 0.1;
+                m
+            })
+            .filter(|m| m.score >= MIN_SCORE)
+            .collect::<Vec<Match>>();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        debug!("found {} history matches", matches.len());
+
+        matches
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|m| HistorySearch::to_suggestion(m.entry, m.count))
+            .collect()
+    }
+
+    // average over the query words of how well each one matches the closest word in the command
+    pub fn get_text_score(query_words: &[String], cmd: &str) -> f64 {
+        let cmd_words = cmd
+            .split(|c: char| c.is_whitespace() || "/.=:@'\"".contains(c))
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<String>>();
+
+        let total: f64 = query_words
+            .iter()
+            .map(|query_word| {
+                cmd_words
+                    .iter()
+                    .map(|cmd_word| HistorySearch::get_word_score(query_word, cmd_word))
+                    .fold(0.0, f64::max)
+            })
+            .sum();
+
+        total / query_words.len() as f64
+    }
+
+    fn get_word_score(query_word: &str, cmd_word: &str) -> f64 {
+        if query_word == cmd_word {
+            1.0
+        } else if cmd_word.starts_with(query_word) {
+            0.8
+        } else if cmd_word.contains(query_word) {
+            0.6
+        } else if HistorySearch::is_subsequence(query_word, cmd_word) {
+            0.4
+        } else {
+            0.0
+        }
+    }
+
+    // "gco" for "gitcheckout", every char of the needle shows up in order
+    fn is_subsequence(needle: &str, haystack: &str) -> bool {
+        let mut haystack = haystack.chars();
+        needle.len() > 1 && needle.chars().all(|c| haystack.any(|h| h == c))
+    }
+
+    fn get_dir_weight(cwd: &str, dir: &str) -> f64 {
+        let cwd = Path::new(cwd);
+        let dir = Path::new(dir);
+
+        if cwd == dir {
+            1.5
+        } else if cwd.starts_with(dir) || dir.starts_with(cwd) {
+            1.2
+        } else {
+            1.0
+        }
+    }
+
+    // up to 1.5 for something run just now, decaying with a half life of about a week. when the
+    // datetime can't be parsed we go by the position in the history instead
+    fn get_recency_weight(
+        entry: &History,
+        position: usize,
+        total: usize,
+        now: NaiveDateTime,
+    ) -> f64 {
+        let age_days = match HistorySearch::parse_datetime(&entry.datetime) {
+            Some(datetime) => (now - datetime).num_hours().max(0) as f64 / 24.0,
+            None => (total - position) as f64,
+        };
+
+        1.0 + 0.5 * (-age_days / 7.0).exp2()
+    }
+
+    fn parse_datetime(datetime: &str) -> Option<NaiveDateTime> {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S") {
+            return Some(datetime);
+        }
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(datetime) {
+            return Some(datetime.with_timezone(&Local).naive_local());
+        }
+        datetime
+            .parse::<i64>()
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|datetime| datetime.with_timezone(&Local).naive_local())
+    }
+
+    // turns the parts of a command that are likely to change between runs into <placeholders>,
+    // returns the template along with the (key, original value) of every placeholder
+    pub fn get_template(cmd: &str) -> (String, Vec<(String, String)>) {
+        let words = match shell_words::split(cmd) {
+            Ok(words) => words,
+            Err(_) => return (cmd.to_string(), vec![]),
+        };
+
+        let mut fields: Vec<(String, String)> = vec![];
+        let mut add_field = |key: &str, value: &str| {
+            let count = fields.iter().filter(|(k, _)| k.starts_with(key)).count();
+            let key = match count {
+                0 => key.to_string(),
+                n => format!("{}_{}", key, n + 1),
+            };
+            fields.push((key, value.to_string()));
+        };
+
+        let mut previous = "";
+        for (i, word) in words.iter().enumerate() {
+            if i == 0 || word.starts_with('-') {
+                // the program itself and flags always stay
+            } else if previous == "-m" || previous == "--message" {
+                add_field("message", word);
+            } else if word.starts_with("http://") || word.starts_with("https://") {
+                add_field("url", word);
+            } else if let Some((host, path)) = HistorySearch::split_remote(word) {
+                add_field("host", host);
+                if !path.is_empty() {
+                    add_field("remote_path", path);
+                }
+            } else if HistorySearch::is_ip(word) {
+                add_field("host", word);
+            } else if word.contains('/') || HistorySearch::has_extension(word) {
+                add_field("path", word);
+            }
+            previous = word;
+        }
+
+        // the values are swapped out in the original string so quoting and chaining stay intact
+        let mut template = cmd.to_string();
+        for (key, value) in &fields {
+            let placeholder = format!("<{}>", key);
+            let quoted_value = [
+                format!("\"{}\"", value),
+                format!("'{}'", value),
+                value.clone(),
+            ]
+            .into_iter()
+            .find(|candidate| template.contains(candidate.as_str()));
+            if let Some(quoted_value) = quoted_value {
+                let placeholder = match key.starts_with("message") {
+                    true => format!("\"{}\"", placeholder),
+                    false => placeholder,
+                };
+                template = template.replacen(&quoted_value, &placeholder, 1);
+            }
+        }
+
+        (template, fields)
+    }
+
+    // user@host, user@host:path and host:path (scp style) all count as remotes, port mappings
+    // like 8080:80 don't
+    fn split_remote(word: &str) -> Option<(&str, &str)> {
+        if word.contains("://") {
+            return None;
+        }
+
+        match word.split_once(':') {
+            Some((host, path))
+                if !host.contains('/') && !host.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                Some((host, path))
+            }
+            _ if word.contains('@') && !word.starts_with('@') => Some((word, "")),
+            _ => None,
+        }
+    }
+
+    fn is_ip(word: &str) -> bool {
+        let parts = word.split('.').collect::<Vec<&str>>();
+        parts.len() == 4 && parts.iter().all(|part| part.parse::<u8>().is_ok())
+    }
+
+    fn has_extension(word: &str) -> bool {
+        match word.rsplit_once('.') {
+            Some((name, ext)) => {
+                !name.is_empty()
+                    && !ext.is_empty()
+                    && ext.chars().all(|c| c.is_ascii_alphanumeric())
+            }
+            None => false,
+        }
+    }
+
+    fn to_suggestion(entry: &History, count: usize) -> ModelSuggestion {
+        let (template, fields) = HistorySearch::get_template(&entry.cmd);
+
+        let missing_fields = fields
+            .into_iter()
+            .map(|(key, value)| MissingField {
+                reasoning: format!(
+                    "the {} used last time might not be the one you want now",
+                    key
+                ),
+                suggestions: vec![MissingFieldSuggestion {
+                    value,
+                    reasoning: format!("used in `{}`", entry.cmd),
+                }],
+                key,
+            })
+            .collect();
+
+        ModelSuggestion {
+            reasoning: format!(
+                "found in your history, run {} time(s), last in {} at {}",
+                count, entry.dir, entry.datetime
+            ),
+            commands: vec![SuggestedCommand {
+                reasoning: format!("you ran `{}` before", entry.cmd),
+                cmd: template,
+                missing_fields,
+            }],
+        }
+    }
+}
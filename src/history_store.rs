@@ -0,0 +1,453 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::models::*;
+use crate::storage::{get_data_dir, get_key};
+
+// what goes into the context from the store, the newest overall and the newest in the cwd
+const MAX_RECENT_HISTORY: usize = 100;
+const MAX_DIR_HISTORY: usize = 50;
+// how much of the start of the log goes into the hash that tells whether it was replaced
+const HEAD_BYTES: u64 = 4096;
+
+// records are numbered by their position in the log, every map points at those numbers. the
+// datetimes sort as strings since they are all %Y-%m-%d %H:%M:%S
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct HistoryIndex {
+    // how far into the log the index goes, anything after it is indexed on the next open
+    indexed_len: u64,
+    // a hash of the start of the log, a log that was replaced by another one doesn't match it
+    head: String,
+    offsets: Vec<u64>,
+    by_dir: BTreeMap<String, Vec<usize>>,
+    by_time: BTreeMap<String, Vec<usize>>,
+    // the same command run again only adds to its list, which is also how often it was run
+    by_cmd: BTreeMap<String, Vec<usize>>,
+    skipped: usize,
+}
+
+// every filter that is set has to match
+#[derive(Default)]
+pub struct HistoryQuery {
+    pub dir: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub prefix: Option<String>,
+}
+
+// an append only jsonl log of the history with an index next to it, a broken record only costs
+// that one record
+pub struct HistoryStore {
+    index: HistoryIndex,
+}
+
+impl HistoryStore {
+    fn get_log_path() -> PathBuf {
+        get_data_dir().join("history.jsonl")
+    }
+
+    fn get_index_path() -> PathBuf {
+        get_data_dir().join("history.index.json")
+    }
+
+    // an empty log doesn't count, history.json is still used then
+    pub fn exists() -> bool {
+        fs::metadata(HistoryStore::get_log_path())
+            .map(|meta| meta.len() > 0)
+            .unwrap_or(false)
+    }
+
+    // the index is caught up with whatever was appended to the log since it was last saved, and
+    // built from scratch when it is missing, broken or for a log that has since been replaced
+    pub fn open() -> HistoryStore {
+        let log_len = fs::metadata(HistoryStore::get_log_path())
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+        let index = fs::read_to_string(HistoryStore::get_index_path())
+            .ok()
+            .and_then(|buf| serde_json::from_str::<HistoryIndex>(&buf).ok())
+            .filter(|index| index.indexed_len <= log_len)
+            .filter(|index| index.head == HistoryStore::get_head(index.indexed_len))
+            .unwrap_or_else(|| {
+                debug!("indexing the history log from the start");
+                HistoryIndex::default()
+            });
+
+        let mut store = HistoryStore { index };
+        if store.catch_up() > 0 {
+            store.save_index();
+        }
+        store
+    }
+
+    fn get_head(len: u64) -> String {
+        let mut buf = vec![];
+        if let Ok(file) = fs::File::open(HistoryStore::get_log_path()) {
+            _ = file.take(len.min(HEAD_BYTES)).read_to_end(&mut buf);
+        }
+        get_key(&[&String::from_utf8_lossy(&buf)])
+    }
+
+    fn catch_up(&mut self) -> usize {
+        let file = match fs::File::open(HistoryStore::get_log_path()) {
+            Ok(file) => file,
+            Err(_) => return 0,
+        };
+        let mut reader = BufReader::new(file);
+        reader
+            .seek(SeekFrom::Start(self.index.indexed_len))
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        let mut indexed = 0;
+        let mut skipped = 0;
+        let mut line = vec![];
+        loop {
+            line.clear();
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .unwrap_or_else(|e| panic!("{}", e));
+            // a last line without a newline is still being written, it is picked up next time
+            if read == 0 || line.last() != Some(&b'\n') {
+                break;
+            }
+
+            let offset = self.index.indexed_len;
+            self.index.indexed_len += read as u64;
+            match serde_json::from_slice::<History>(&line) {
+                Ok(history) => {
+                    self.add_to_index(offset, &history);
+                    indexed += 1;
+                }
+                Err(_) if line.trim_ascii().is_empty() => {}
+                Err(e) => {
+                    debug!("skipping broken history record at {}: {}", offset, e);
+                    skipped += 1;
+                }
+            }
+        }
+
+        if skipped > 0 {
+            warn!("skipped {} broken records in the history log", skipped);
+            self.index.skipped += skipped;
+        }
+        debug!("indexed {} new history records", indexed);
+        indexed + skipped
+    }
+
+    fn add_to_index(&mut self, offset: u64, history: &History) {
+        let id = self.index.offsets.len();
+        self.index.offsets.push(offset);
+        for (map, key) in [
+            (&mut self.index.by_dir, &history.dir),
+            (&mut self.index.by_time, &history.datetime),
+            (&mut self.index.by_cmd, &history.cmd),
+        ] {
+            map.entry(key.clone()).or_default().push(id);
+        }
+    }
+
+    fn save_index(&mut self) {
+        self.index.head = HistoryStore::get_head(self.index.indexed_len);
+        let path = HistoryStore::get_index_path();
+        let tmp_path = path.with_extension("json.tmp");
+        let buf = serde_json::to_string(&self.index).unwrap_or_else(|e| panic!("{}", e));
+        fs::write(&tmp_path, buf).unwrap_or_else(|e| panic!("{}", e));
+        fs::rename(&tmp_path, &path).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.offsets.len()
+    }
+
+    pub fn append(&mut self, entries: &[History]) {
+        HistoryStore::write(entries);
+        self.catch_up();
+        self.save_index();
+    }
+
+    // what the shell hook runs after every command, only the log is written and the index
+    // catches up with it on the next open
+    pub fn add(history: &History) {
+        HistoryStore::write(std::slice::from_ref(history));
+    }
+
+    fn write(entries: &[History]) {
+        let path = HistoryStore::get_log_path();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        // a record that was cut short gets a line of its own, so it can't take the next one with it
+        let len = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        let mut last = [b'\n'];
+        if len > 0 {
+            file.seek(SeekFrom::Start(len - 1))
+                .and_then(|_| file.read_exact(&mut last))
+                .unwrap_or_else(|e| panic!("{}", e));
+        }
+        let mut buf = match last[0] {
+            b'\n' => String::new(),
+            _ => "\n".to_string(),
+        };
+        for history in entries {
+            buf.push_str(&serde_json::to_string(history).unwrap_or_else(|e| panic!("{}", e)));
+            buf.push('\n');
+        }
+        file.write_all(buf.as_bytes())
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    // the old history.json format, records that are already in the store are left out so
+    // importing the same file twice doesn't count everything double
+    pub fn import(&mut self, path: &Path) -> Result<(usize, usize), String> {
+        let buf = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let (history, skipped) = HistoryStore::parse_json(&buf);
+
+        let new = self.get_new(history);
+        self.append(&new);
+        Ok((new.len(), skipped))
+    }
+
+    // the records that aren't in the store yet, looked up in the index without reading the log
+    fn get_new(&self, history: Vec<History>) -> Vec<History> {
+        let mut seen = HashSet::new();
+        history
+            .into_iter()
+            .filter(|history| !self.contains(history))
+            .filter(|history| {
+                seen.insert((
+                    history.datetime.clone(),
+                    history.dir.clone(),
+                    history.cmd.clone(),
+                ))
+            })
+            .collect()
+    }
+
+    // the id lists are in log order, so they can be searched
+    fn contains(&self, history: &History) -> bool {
+        let index = &self.index;
+        match (
+            index.by_time.get(&history.datetime),
+            index.by_dir.get(&history.dir),
+            index.by_cmd.get(&history.cmd),
+        ) {
+            (Some(time_ids), Some(dir_ids), Some(cmd_ids)) => time_ids
+                .iter()
+                .any(|id| dir_ids.binary_search(id).is_ok() && cmd_ids.binary_search(id).is_ok()),
+            _ => false,
+        }
+    }
+
+    // a json array of history records. when it doesn't parse as a whole every object in it is
+    // tried on its own, so one broken record doesn't take the rest with it
+    pub fn parse_json(buf: &str) -> (Vec<History>, usize) {
+        let values = match serde_json::from_str::<Vec<serde_json::Value>>(buf) {
+            Ok(values) => values.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => {
+                warn!(
+                    "the history is not valid json, reading it record by record: {}",
+                    e
+                );
+                HistoryStore::split_objects(buf)
+                    .into_iter()
+                    .map(serde_json::from_str::<serde_json::Value>)
+                    .collect()
+            }
+        };
+
+        let mut history = vec![];
+        let mut skipped = 0;
+        for value in values {
+            match value.and_then(serde_json::from_value::<History>) {
+                Ok(entry) => history.push(entry),
+                Err(e) => {
+                    debug!("skipping broken history record: {}", e);
+                    skipped += 1;
+                }
+            }
+        }
+        if skipped > 0 {
+            warn!("skipped {} broken history records", skipped);
+        }
+        (history, skipped)
+    }
+
+    // the text of every {...}, braces inside strings don't count. records are flat, so a { while
+    // one is still open means that one was cut short and it is handed back broken as it is
+    fn split_objects(buf: &str) -> Vec<&str> {
+        let mut objects = vec![];
+        let mut start: Option<usize> = None;
+        let (mut in_string, mut escaped) = (false, false);
+        for (i, c) in buf.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                // strings can't span lines, one left open ends with its line
+                '\n' if in_string => in_string = false,
+                '"' => in_string = !in_string,
+                '{' if !in_string => {
+                    if let Some(start) = start {
+                        objects.push(&buf[start..i]);
+                    }
+                    start = Some(i);
+                }
+                '}' if !in_string => {
+                    if let Some(start) = start.take() {
+                        objects.push(&buf[start..=i]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        objects
+    }
+
+    // records in log order. the reader only seeks when the next id isn't the very next record,
+    // so a run of ids is read straight through
+    pub fn get(&self, ids: &[usize]) -> Vec<History> {
+        let mut file = match fs::File::open(HistoryStore::get_log_path()) {
+            Ok(file) => BufReader::new(file),
+            Err(_) => return vec![],
+        };
+
+        let mut history = vec![];
+        let mut line = String::new();
+        let mut position = 0;
+        for id in ids {
+            line.clear();
+            let offset = match self.index.offsets.get(*id) {
+                Some(offset) => *offset,
+                None => continue,
+            };
+            if offset != position && file.seek_relative(offset as i64 - position as i64).is_err() {
+                continue;
+            }
+            match file.read_line(&mut line) {
+                Ok(read) => position = offset + read as u64,
+                Err(_) => continue,
+            }
+            match serde_json::from_str::<History>(&line) {
+                Ok(entry) => history.push(entry),
+                Err(e) => warn!("the history log changed under its index: {}", e),
+            }
+        }
+        history
+    }
+
+    // every record front to back, without the index since nothing has to be looked up
+    pub fn read_all() -> Vec<History> {
+        let file = match fs::File::open(HistoryStore::get_log_path()) {
+            Ok(file) => file,
+            Err(_) => return vec![],
+        };
+
+        let mut skipped = 0;
+        let history = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<History>(&line) {
+                Ok(history) => Some(history),
+                Err(_) => {
+                    skipped += 1;
+                    None
+                }
+            })
+            .collect::<Vec<History>>();
+        debug!("read {} history records, {} broken", history.len(), skipped);
+        history
+    }
+
+    pub fn find(&self, query: &HistoryQuery) -> Vec<usize> {
+        let mut found: Option<HashSet<usize>> = None;
+        let mut narrow = |ids: Vec<usize>| {
+            let ids = ids.into_iter().collect::<HashSet<usize>>();
+            found = Some(match found.take() {
+                Some(found) => found.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        };
+
+        if let Some(dir) = &query.dir {
+            narrow(self.index.by_dir.get(dir).cloned().unwrap_or_default());
+        }
+        if query.since.is_some() || query.until.is_some() {
+            narrow(self.find_by_time(query.since.as_deref(), query.until.as_deref()));
+        }
+        if let Some(prefix) = &query.prefix {
+            narrow(
+                self.index
+                    .by_cmd
+                    .range(prefix.clone()..)
+                    .take_while(|(cmd, _)| cmd.starts_with(prefix.as_str()))
+                    .flat_map(|(_, ids)| ids.iter().copied())
+                    .collect(),
+            );
+        }
+
+        let mut ids = match found {
+            Some(found) => found.into_iter().collect::<Vec<usize>>(),
+            None => (0..self.len()).collect(),
+        };
+        ids.sort();
+        ids
+    }
+
+    // both ends are inclusive and can be cut short, until 2024-11-04 takes in the whole day
+    fn find_by_time(&self, since: Option<&str>, until: Option<&str>) -> Vec<usize> {
+        self.index
+            .by_time
+            .range(since.unwrap_or_default().to_string()..)
+            .take_while(|(datetime, _)| match until {
+                Some(until) => datetime.get(..until.len()).unwrap_or(datetime) <= until,
+                None => true,
+            })
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect()
+    }
+
+    // how often each command was run among the ids, the most used first
+    pub fn get_counts(&self, ids: &[usize]) -> Vec<(String, usize)> {
+        let ids = ids.iter().collect::<HashSet<&usize>>();
+        let mut counts = self
+            .index
+            .by_cmd
+            .iter()
+            .map(|(cmd, cmd_ids)| {
+                let count = cmd_ids.iter().filter(|id| ids.contains(id)).count();
+                (cmd.clone(), count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect::<Vec<(String, usize)>>();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    // the newest records overall and the newest in the cwd, oldest first like the history file
+    pub fn get_for_context(&self, cwd: &str) -> Vec<History> {
+        let dir_ids = self.find(&HistoryQuery {
+            dir: Some(cwd.to_string()),
+            ..HistoryQuery::default()
+        });
+        let mut ids = dir_ids
+            .iter()
+            .rev()
+            .take(MAX_DIR_HISTORY)
+            .copied()
+            .chain((0..self.len()).rev().take(MAX_RECENT_HISTORY))
+            .collect::<Vec<usize>>();
+        ids.sort();
+        ids.dedup();
+        self.get(&ids)
+    }
+}
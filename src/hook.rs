@@ -0,0 +1,77 @@
+use std::env;
+
+use crate::aliases::Alias;
+
+pub struct Hook;
+
+impl Hook {
+    // shell code to eval in the rc file. after every command it writes the exit status, the dir
+    // and the command to $ZLI_HOME/last_command, which is what the fix subcommand picks up, and
+    // adds the command to the history store. the
+    // shell's stderr is left alone, redirecting it for good breaks `[ -t 2 ]` and the prompt, so
+    // the error output comes from re-running the command. saved aliases are defined as shell
+    // functions
+    pub fn get_script(shell: &str) -> String {
+        let aliases = Alias::load_all();
+        let functions = match aliases.is_empty() {
+            true => String::new(),
+            false => format!("\n# saved aliases\n{}", Alias::get_functions(&aliases)),
+        };
+        let bin = env::current_exe()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| "rust-cli".to_string());
+        let common = format!(
+            r#"_zli_dir="${{ZLI_HOME:-$HOME/.zli}}"
+_zli_bin={bin}
+mkdir -p "$_zli_dir"
+
+_zli_save_last_command() {{
+    printf '%s\n%s\n%s\n' "$1" "$PWD" "$2" > "$_zli_dir/last_command"
+    "$_zli_bin" history add --dir "$PWD" -- "$2" 2>/dev/null
+}}
+"#,
+            bin = shell_words::quote(&bin)
+        );
+
+        let script = match shell {
+            "zsh" => format!(
+                r#"{common}
+_zli_preexec() {{
+    _zli_cmd="$1"
+}}
+
+_zli_precmd() {{
+    local exit_status=$?
+    [ -z "$_zli_cmd" ] && return
+    _zli_save_last_command "$exit_status" "$_zli_cmd"
+    _zli_cmd=""
+}}
+
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec _zli_preexec
+add-zsh-hook precmd _zli_precmd
+"#,
+                common = common
+            ),
+            "bash" => format!(
+                r#"{common}
+_zli_precmd() {{
+    local exit_status=$?
+    local cmd
+    cmd="$(HISTTIMEFORMAT= history 1 | sed 's/^ *[0-9]* *//')"
+    [ -n "$cmd" ] && _zli_save_last_command "$exit_status" "$cmd"
+}}
+
+PROMPT_COMMAND="_zli_precmd${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+"#,
+                common = common
+            ),
+            _ => panic!(
+                "unsupported shell {}, only bash and zsh are supported",
+                shell
+            ),
+        };
+
+        format!("{}{}", script, functions)
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{debug, warn};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, to_value, Value};
+
+use crate::backend::BackendError;
+use crate::context::{ContextConfig, ContextProvider, ProviderSettings};
+use crate::models::*;
+use crate::redact::Redactor;
+use crate::storage::get_data_dir;
+
+// embedding a command while building the index can take a while, the model may still be loading
+const EMBED_TIMEOUT: Duration = Duration::from_secs(30);
+
+// what [providers.similar] in context.toml can set on top of the usual settings
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct IndexOptions {
+    pub url: String,
+    pub model: String,
+    pub top_k: usize,
+}
+
+impl Default for IndexOptions {
+    fn default() -> IndexOptions {
+        IndexOptions {
+            url: "http://localhost:11434/api/embeddings".to_string(),
+            model: "nomic-embed-text".to_string(),
+            top_k: 5,
+        }
+    }
+}
+
+// one line of the index file, the model is kept so switching models doesn't mix up vectors
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    model: String,
+    cmd: String,
+    dir: String,
+    embedding: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SimilarCommand {
+    pub cmd: String,
+    pub dir: String,
+    pub score: f64,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    embedding: Vec<f32>,
+}
+
+// embeddings of every distinct command and dir in the history, appended to a jsonl file by
+// `index rebuild` and `index update`, never while answering a query
+pub struct EmbeddingIndex {
+    options: IndexOptions,
+    entries: Vec<IndexEntry>,
+    redactor: Redactor,
+}
+
+impl EmbeddingIndex {
+    pub fn get_path() -> PathBuf {
+        get_data_dir().join("embeddings.jsonl")
+    }
+
+    // None when there is no index, it is only used once `index rebuild` created it
+    pub fn load(options: &IndexOptions) -> Option<EmbeddingIndex> {
+        let buf = fs::read_to_string(EmbeddingIndex::get_path()).ok()?;
+
+        // a line cut short by a run that was stopped halfway is skipped, not fatal
+        let mut skipped = 0;
+        let entries = buf
+            .lines()
+            .filter_map(|line| match serde_json::from_str::<IndexEntry>(line) {
+                Ok(entry) => Some(entry),
+                Err(_) => {
+                    skipped += 1;
+                    None
+                }
+            })
+            .filter(|entry| entry.model == options.model)
+            .collect::<Vec<IndexEntry>>();
+        if skipped > 0 {
+            warn!("skipped {} broken lines in the embeddings index", skipped);
+        }
+        debug!("loaded {} embeddings", entries.len());
+
+        Some(EmbeddingIndex {
+            options: options.clone(),
+            entries,
+            redactor: Redactor::load(),
+        })
+    }
+
+    // the text goes through the redaction like everything else that is sent to a model
+    fn embed(&mut self, text: &str, timeout: Duration) -> Result<Vec<f32>, BackendError> {
+        let prompt = self.redactor.redact(text);
+        let response = match Client::new()
+            .post(&self.options.url)
+            .json(&json!({"model": self.options.model, "prompt": prompt}))
+            .timeout(timeout)
+            .send()
+        {
+            Ok(response) => response,
+            Err(e) => return Err(BackendError::Unavailable(e.to_string())),
+        };
+
+        response
+            .json::<EmbeddingResponse>()
+            .map(|response| response.embedding)
+            .map_err(|e| BackendError::Unavailable(e.to_string()))
+    }
+
+    fn get_text(cmd: &str, dir: &str) -> String {
+        format!("{} in {}", cmd, dir)
+    }
+
+    // embeds the history entries that aren't in the index yet, newest first
+    pub fn update(&mut self, history: &[History]) -> Result<usize, BackendError> {
+        let mut known = self
+            .entries
+            .iter()
+            .map(|entry| (entry.cmd.clone(), entry.dir.clone()))
+            .collect::<HashSet<(String, String)>>();
+        let missing = history
+            .iter()
+            .rev()
+            .filter(|history| known.insert((history.cmd.clone(), history.dir.clone())))
+            .collect::<Vec<&History>>();
+        if missing.is_empty() {
+            return Ok(0);
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(EmbeddingIndex::get_path())
+            .unwrap_or_else(|e| panic!("{}", e));
+        let mut added = 0;
+        for history in missing {
+            let entry = IndexEntry {
+                model: self.options.model.clone(),
+                cmd: history.cmd.clone(),
+                dir: history.dir.clone(),
+                embedding: self.embed(
+                    &EmbeddingIndex::get_text(&history.cmd, &history.dir),
+                    EMBED_TIMEOUT,
+                )?,
+            };
+            let line = serde_json::to_string(&entry).unwrap_or_else(|e| panic!("{}", e));
+            writeln!(file, "{}", line).unwrap_or_else(|e| panic!("{}", e));
+            self.entries.push(entry);
+            added += 1;
+        }
+        debug!("added {} commands to the embeddings index", added);
+        Ok(added)
+    }
+
+    // starts over with an empty index and embeds the whole history
+    pub fn rebuild(options: &IndexOptions, history: &[History]) -> Result<usize, BackendError> {
+        fs::write(EmbeddingIndex::get_path(), "").unwrap_or_else(|e| panic!("{}", e));
+        let mut index = EmbeddingIndex {
+            options: options.clone(),
+            entries: vec![],
+            redactor: Redactor::load(),
+        };
+        index.update(history)
+    }
+
+    // the top k by cosine similarity, least similar first like the history is oldest first, so
+    // trimming to the token budget keeps the closest
+    pub fn search(
+        &mut self,
+        query: &str,
+        timeout: Duration,
+    ) -> Result<Vec<SimilarCommand>, BackendError> {
+        let embedding = self.embed(query, timeout)?;
+        let mut similar = self
+            .entries
+            .iter()
+            .map(|entry| SimilarCommand {
+                cmd: entry.cmd.clone(),
+                dir: entry.dir.clone(),
+                score: EmbeddingIndex::get_similarity(&embedding, &entry.embedding) as f64,
+            })
+            .collect::<Vec<SimilarCommand>>();
+        similar.sort_by(|a, b| b.score.total_cmp(&a.score));
+        similar.truncate(self.options.top_k);
+        similar.reverse();
+
+        // two decimals are all the model needs to tell them apart
+        for command in similar.iter_mut() {
+            command.score = (command.score * 100.0).round() / 100.0;
+        }
+        Ok(similar)
+    }
+
+    fn get_similarity(a: &[f32], b: &[f32]) -> f32 {
+        let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        match norm(a) * norm(b) {
+            0.0 => 0.0,
+            norms => dot / norms,
+        }
+    }
+}
+
+// past commands that mean the same as the query even when they share no words with it, only
+// when there is an index. only the query is embedded here, so it fits in the time the provider
+// has
+pub struct SimilarProvider {
+    query: String,
+    settings: ProviderSettings,
+    options: IndexOptions,
+}
+
+impl SimilarProvider {
+    pub fn load(config: &ContextConfig, query: &str) -> SimilarProvider {
+        SimilarProvider {
+            query: query.to_string(),
+            settings: config.get_settings("similar", 2000, 300),
+            options: config.get_options("similar"),
+        }
+    }
+}
+
+impl ContextProvider for SimilarProvider {
+    fn name(&self) -> &str {
+        "similar"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, _cwd: &Path) -> Option<Value> {
+        if self.query.is_empty() {
+            return None;
+        }
+        let mut index = EmbeddingIndex::load(&self.options)?;
+        match index.search(&self.query, Duration::from_millis(self.settings.timeout_ms)) {
+            Ok(similar) if !similar.is_empty() => to_value(similar).ok(),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("unable to search the embeddings index: {}", e);
+                None
+            }
+        }
+    }
+}
@@ -1,20 +1,72 @@
-use std::{env, fs};
-use std::io::{Read, stdin, stdout, Write};
+use std::io::{stdin, stdout, Write};
+use std::path::Path;
 use std::process::ExitStatus;
-use std::time::Duration;
+use std::{env, fs};
 
+use chrono::Local;
 use clap::{arg, Arg, ArgAction, ArgMatches, Command};
-use env_logger;
-use log::{debug, trace};
-use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
-use serde::de::DeserializeOwned;
+use log::{debug, trace, warn};
 use serde_json::{from_str, to_string};
 
 // use serde_json::Value::String;
+use agent::*;
+use aliases::*;
+use backend::*;
+use cache::*;
+use containers::*;
+use context::*;
+use environment::*;
+use explain::*;
+use feedback::*;
+use fix::*;
+use history_search::*;
+use history_store::*;
+use hook::*;
+use index::*;
 use models::*;
+use prompts::*;
+use providers::*;
+use ranking::*;
+use redact::*;
+use safety::*;
+use script::*;
+use session::*;
+use snippets::*;
+use ssh::*;
+use stats::*;
+use verify::*;
 
+mod agent;
+mod aliases;
+mod backend;
+mod cache;
+mod containers;
+mod context;
+mod environment;
+mod excerpts;
+mod explain;
+mod feedback;
+mod filesystem;
+mod fix;
+mod history_search;
+mod history_store;
+mod hook;
+mod index;
 mod models;
+mod prompts;
+mod providers;
+mod ranking;
+mod redact;
+mod safety;
+mod script;
+mod session;
+mod snippets;
+mod ssh;
+mod stats;
+mod storage;
+mod verify;
+
+const MODEL: &str = "qwen2.5";
 
 fn main() {
     // set log level from args
@@ -30,27 +82,354 @@ fn main() {
                 .action(ArgAction::Append)
                 .default_value("debug")
                 .default_missing_value("debug")
-                .num_args(1),
+                .num_args(1)
+                .global(true),
+        )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .value_name("FILE")
+                .help("json file with the users command history, defaults to the history store in the data dir"),
+        )
+        .arg(
+            Arg::new("backend")
+                .short('b')
+                .long("backend")
+                .value_name("BACKEND")
+                .value_parser(["ollama", "dummy"])
+                .default_value("dummy")
+                .help("where to get the model response from"),
+        )
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .value_name("URL")
+                .default_value("http://localhost:11434/api/chat")
+                .help("ollama chat endpoint used by the ollama backend"),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .value_name("DIR")
+                .conflicts_with("replay")
+                .help("save every request/response pair as a cassette in DIR"),
+        )
+        .arg(
+            Arg::new("replay")
+                .long("replay")
+                .value_name("DIR")
+                .help("serve responses from the cassettes in DIR instead of calling the model"),
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .action(ArgAction::SetTrue)
+                .help("print token usage and latency after the query"),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .action(ArgAction::SetTrue)
+                .help("always ask the model, even if the same query was answered before"),
+        )
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .action(ArgAction::SetTrue)
+                .help("don't ask the model, only search the command history"),
+        )
+        .arg(
+            Arg::new("show-context")
+                .long("show-context")
+                .action(ArgAction::SetTrue)
+                .help("print exactly what is sent to the model, after secrets are redacted"),
+        )
+        .arg(
+            Arg::new("no-verify")
+                .long("no-verify")
+                .action(ArgAction::SetTrue)
+                .help("don't check the suggested flags against the --help output of each program"),
+        )
+        .arg(
+            Arg::new("chat")
+                .long("chat")
+                .action(ArgAction::SetTrue)
+                .help("keep refining the suggestions with follow up messages"),
+        )
+        .arg(
+            Arg::new("resume")
+                .long("resume")
+                .value_name("ID")
+                .help("continue a saved chat session, the query becomes the first follow up"),
+        )
+        .arg(
+            Arg::new("agent")
+                .long("agent")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["chat", "resume"])
+                .help(
+                    "work through the query one command at a time, showing the model every output",
+                ),
+        )
+        .arg(
+            Arg::new("max-steps")
+                .long("max-steps")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("the most commands the agent runs before it is stopped"),
+        )
+        .arg(
+            Arg::new("emit-script")
+                .long("emit-script")
+                .value_name("PATH")
+                .help(
+                    "write the selected suggestion to an executable script instead of running it",
+                ),
         )
         .arg(
             arg!([input] "users query")
                 .trailing_var_arg(true)
                 .num_args(1..),
         )
+        .subcommand(Command::new("stats").about("summarize model usage per model and day"))
+        .subcommand(
+            Command::new("cache")
+                .about("manage the response cache")
+                .subcommand_required(true)
+                .subcommand(Command::new("clear").about("remove every cached response")),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("manage the history store in the data dir, used instead of history.json once it has anything")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("add a command to the store, the shell hook runs this after every command")
+                        .arg(arg!(<cmd> "the command that was run"))
+                        .arg(
+                            Arg::new("dir")
+                                .long("dir")
+                                .value_name("DIR")
+                                .help("the dir it was run in, defaults to the cwd"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("import")
+                        .about("add the records of a history.json file to the store, once when moving over to it")
+                        .arg(arg!(<file> "json file with an array of history records")),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("list the stored commands, oldest first")
+                        .arg(
+                            Arg::new("dir")
+                                .long("dir")
+                                .value_name("DIR")
+                                .help("only the ones run in DIR"),
+                        )
+                        .arg(
+                            Arg::new("since")
+                                .long("since")
+                                .value_name("DATETIME")
+                                .help("only the ones run at or after DATETIME, like 2024-11-04 or 2024-11-04 06:13"),
+                        )
+                        .arg(
+                            Arg::new("until")
+                                .long("until")
+                                .value_name("DATETIME")
+                                .help("only the ones run at or before DATETIME"),
+                        )
+                        .arg(
+                            Arg::new("prefix")
+                                .long("prefix")
+                                .value_name("PREFIX")
+                                .help("only the commands that start with PREFIX"),
+                        )
+                        .arg(
+                            Arg::new("counts")
+                                .long("counts")
+                                .action(ArgAction::SetTrue)
+                                .help("every distinct command once, with how often it was run"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("index")
+                .about("manage the embeddings index of the history, used once it is built")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("rebuild")
+                        .about("embed the whole history again with the model in context.toml"),
+                )
+                .subcommand(
+                    Command::new("update")
+                        .about("embed the history that was added since the index was built"),
+                ),
+        )
+        .subcommand(Command::new("sessions").about("list the saved chat sessions"))
+        .subcommand(
+            Command::new("fix")
+                .about("suggest a fix for the last command that was run")
+                .arg(
+                    Arg::new("rerun")
+                        .long("rerun")
+                        .action(ArgAction::SetTrue)
+                        .help("run the command again to capture its error output without asking"),
+                ),
+        )
+        .subcommand(
+            Command::new("hook")
+                .about("print the shell hook, add `eval \"$(rust-cli hook zsh)\"` to your rc file")
+                .arg(arg!(<shell> "shell to print the hook for").value_parser(["bash", "zsh"])),
+        )
+        .subcommand(
+            Command::new("aliases")
+                .about("manage the saved aliases, save one with `save <name>` when picking a suggestion")
+                .subcommand(Command::new("list").about("list the saved aliases"))
+                .subcommand(
+                    Command::new("remove")
+                        .about("remove a saved alias")
+                        .arg(arg!(<name> "name of the alias")),
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("print the saved aliases as shell functions or as json")
+                        .arg(
+                            Arg::new("format")
+                                .long("format")
+                                .value_parser(["shell", "json"])
+                                .default_value("shell"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("prompt")
+                .about("print the prompt that would be sent for a query")
+                .arg(
+                    Arg::new("init")
+                        .long("init")
+                        .action(ArgAction::SetTrue)
+                        .help("copy the built in templates to the prompts dir in the data dir to edit them"),
+                )
+                .arg(
+                    arg!([query] "query to render the prompt for")
+                        .trailing_var_arg(true)
+                        .num_args(1..),
+                ),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("explain what a command does, stage by stage")
+                .arg(
+                    arg!(<command> "command to explain")
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true)
+                        .num_args(1..),
+                ),
+        )
         .get_matches();
 
     set_log_level(&matcher);
 
-    let user_query = matcher
-        .try_get_many::<String>("input")
-        .unwrap_or_else(|err| panic!("{}", err))
-        .unwrap_or_else(|| panic!("no user input"))
-        .cloned()
-        .collect::<Vec<String>>()
-        .join(" ");
+    match matcher.subcommand() {
+        Some(("stats", _)) => {
+            Stats::print_summary(&Stats::load());
+            return;
+        }
+        Some(("cache", sub_matcher)) => {
+            if let Some(("clear", _)) = sub_matcher.subcommand() {
+                println!("removed {} cached responses", ResponseCache::clear());
+            }
+            return;
+        }
+        Some(("history", sub_matcher)) => {
+            run_history(sub_matcher);
+            return;
+        }
+        Some(("index", sub_matcher)) => {
+            match sub_matcher.subcommand() {
+                Some(("rebuild", _)) => run_index(&matcher, true),
+                Some(("update", _)) => run_index(&matcher, false),
+                _ => {}
+            }
+            return;
+        }
+        Some(("explain", sub_matcher)) => {
+            let cmd = sub_matcher
+                .get_many::<String>("command")
+                .unwrap_or_else(|| panic!("no command to explain"))
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(" ");
+            run_explain(&matcher, &cmd);
+            return;
+        }
+        Some(("fix", sub_matcher)) => {
+            run_fix(&matcher, sub_matcher);
+            return;
+        }
+        Some(("hook", sub_matcher)) => {
+            let shell = sub_matcher
+                .get_one::<String>("shell")
+                .unwrap_or_else(|| panic!("no shell given"));
+            print!("{}", Hook::get_script(shell));
+            return;
+        }
+        Some(("prompt", sub_matcher)) => {
+            run_prompt(&matcher, sub_matcher);
+            return;
+        }
+        Some(("aliases", sub_matcher)) => {
+            run_aliases(sub_matcher);
+            return;
+        }
+        Some(("sessions", _)) => {
+            for session in Session::list() {
+                println!(
+                    "{} {} {}",
+                    session.id,
+                    session.created_at,
+                    session.get_first_query()
+                );
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    if let Some(id) = matcher.get_one::<String>("resume") {
+        let mut session = Session::load(id);
+        println!("resuming session {}", session.id);
+        print_suggestions(&session.get_last_suggestions());
+        let history_file_path = matcher
+            .get_one::<String>("history")
+            .cloned()
+            .unwrap_or_default();
+        let query = get_user_query(&matcher);
+        let context = init_and_get_context(
+            history_file_path,
+            &query.clone().unwrap_or_else(|| session.get_first_query()),
+        );
+        run_chat(&matcher, &mut session, query, &context);
+        select_and_execute(
+            &matcher,
+            &session.get_first_query(),
+            &session.get_last_suggestions(),
+        );
+        return;
+    }
 
-    let context = init_and_get_context("/Users/shubham/.temp_his".to_string());
-    let system_prompt = Prompts::get_system_prompt_2(&context);
+    let user_query = get_user_query(&matcher).unwrap_or_else(|| panic!("no user input"));
+
+    let history_file_path = matcher
+        .get_one::<String>("history")
+        .cloned()
+        .unwrap_or_default();
+    let context = init_and_get_context(history_file_path, &user_query);
+    let snippet_suggestions = get_snippet_suggestions(&user_query);
+    let examples = get_examples(&user_query, &context);
+    let system_prompt = Prompts::get_system_prompt(&context, &snippet_suggestions, &examples);
 
     trace!("user query is {}", user_query);
     trace!(
@@ -59,8 +438,22 @@ fn main() {
     );
     trace!("system prompt is {}", system_prompt);
 
+    if matcher.get_flag("agent") {
+        run_agent(&matcher, user_query, &context);
+        return;
+    }
+
+    let model = MODEL.to_string();
+    let cache_key = ResponseCache::key(
+        &user_query,
+        &model,
+        &context,
+        &snippet_suggestions,
+        &examples,
+    );
+
     let request_body = OllamaRequest {
-        model: "qwen2.5".to_string(),
+        model,
         format: "json".to_string(),
         stream: false,
         messages: vec![
@@ -70,122 +463,851 @@ fn main() {
             },
             OllamaMessage {
                 role: "user".to_string(),
-                content: user_query,
+                content: user_query.clone(),
             },
         ],
     };
 
-    // let response_text = Client::new()
-    //     .post("http://localhost:11434/api/chat")
-    //     .json(&request_body)
-    //     .timeout(Duration::from_secs(360))
-    //     .send()
-    //     .unwrap_or_else(|e| panic!("{}", e))
-    //     .text() // we can directly use json() as well, and specify the type after the let
-    //     // the issue is that the error messages are not clear in that case, thats why it is a 2 step process
-    //     .unwrap_or_else(|e| panic!("{}", e));
-    let response_text = DummyResponse::get_dummy_response();
-    trace!("raw response is {}", response_text);
+    // record/replay runs are there to exercise the backend, so they always skip the cache
+    let use_cache = !matcher.get_flag("no-cache")
+        && matcher.get_one::<String>("record").is_none()
+        && matcher.get_one::<String>("replay").is_none();
+    let cached_response = match use_cache {
+        true => ResponseCache::get(&cache_key),
+        false => None,
+    };
 
-    let response = from_str::<OllamaResponse>(&response_text).unwrap_or_else(|e| panic!("{}", e));
-    debug!(
-        "response is {}",
-        to_string(&response).unwrap_or("unable to deserialize response".to_string())
-    );
+    let suggestions: Vec<ModelSuggestion> = match cached_response {
+        Some(response) => {
+            if matcher.get_flag("stats") {
+                println!("cached response, no model usage");
+            }
+            response.response
+        }
+        None => {
+            let response = match matcher.get_flag("offline") {
+                true => None,
+                false => get_model_response(&matcher, &request_body, &context),
+            };
 
-    let suggestions: Vec<ModelSuggestion> =
-        from_str::<OllamaPlaceholderResponse>(&response.message.content)
-            .unwrap_or_else(|e| panic!("{}", e))
-            .response;
+            match response {
+                Some(response) => {
+                    if use_cache && !response.response.is_empty() {
+                        ResponseCache::put(&cache_key, &user_query, &request_body.model, &response);
+                    }
+                    response.response
+                }
+                None => {
+                    debug!("no model available, searching the history instead");
+                    let mut suggestions = HistorySearch::get_suggestions(&user_query, &context);
+                    add_context_suggestions(&mut suggestions, &context);
+                    suggestions
+                }
+            }
+        }
+    };
     debug!(
         "suggestions are \n {}",
         to_string(&suggestions).unwrap_or("unable to deserialize suggestions".to_string())
     );
 
+    // matching snippets are listed first so they can be picked like any other suggestion
+    let suggestions = [snippet_suggestions, suggestions].concat();
+    print_suggestions(&suggestions);
+    let suggestions = match matcher.get_flag("chat") {
+        true => {
+            let mut session = Session::new(&request_body.model, request_body.messages);
+            let response = OllamaPlaceholderResponse {
+                response: suggestions,
+            };
+            session.push(
+                "assistant",
+                to_string(&response).unwrap_or_else(|e| panic!("{}", e)),
+            );
+            session.save();
+            println!("session {}", session.id);
+
+            run_chat(&matcher, &mut session, None, &context);
+            session.get_last_suggestions()
+        }
+        false => suggestions,
+    };
+
     // let content = r#"
     //  {"model":"qwen2.5","created_at":"2024-11-04T02:50:52.832969Z","message":{"role":"assistant","content":"{\n    \"response\": [\n        {\n            \"reasoning\": \"Based on the user's history, it seems they might be working on a Rust project and need to build or run it.\",\n            \"commands\": [\n                {\n                    \"cmd\": \"cargo build\",\n                    \"missing_fields\": [],\n                    \"reasoning\": \"This command builds the project. Since no missing fields are present, we can directly suggest this.\"\n                },\n                {\n                    \"cmd\": \"cargo run\",\n                    \"missing_fields\": [],\n                    \"reasoning\": \"After building, running the project is a common next step. No missing fields needed here.\"\n                }\n            ]\n        },\n        {\n            \"reasoning\": \"The user might want to open their `Cargo.toml` file in an editor since they are working on a Rust project.\",\n            \"commands\": [\n                {\n                    \"cmd\": \"nvim Cargo.toml\",\n                    \"missing_fields\": [],\n                    \"reasoning\": \"Opening the `Cargo.toml` file for potential modifications is likely.\"\n                }\n            ]\n        },\n        {\n            \"reasoning\": \"Given the presence of a `.git` directory, it's possible that the user wants to manage their project using Git.\",\n            \"commands\": [\n                {\n                    \"cmd\": \"git status\",\n                    \"missing_fields\": [],\n                    \"reasoning\": \"Checking the current state of the repository is a common first step after working on code.\"\n                },\n                {\n                    \"cmd\": \"git add .; git commit -m 'Adding changes to src directory'; git push\",\n                    \"missing_fields\": [\n                        {\"field\": \"commit_message\", \"suggested_value\": \"Adding changes to src directory\"}\n                    ],\n                    \"reasoning\": \"After making changes, committing and pushing them are typical next steps.\"\n                }\n            ]\n        },\n        {\n            \"reasoning\": \"The user might want to test their project locally or on another machine.\",\n            \"commands\": [\n                {\n                    \"cmd\": \"cargo test\",\n                    \"missing_fields\": [],\n                    \"reasoning\": \"Running tests is a common practice after making changes.\"\n                }\n            ]\n        },\n        {\n            \"reasoning\": \"The user might be interested in exploring the `src` directory to understand its contents.\",\n            \"commands\": [\n                {\n                    \"cmd\": \"tree src\",\n                    \"missing_fields\": [],\n                    \"reasoning\": \"Listing the contents of the `src` directory can help explore the project structure.\"\n                }\n            ]\n        },\n        {\n            \"reasoning\": \"Given the presence of a `.gitignore` file, it's possible that the user wants to ensure their files are tracked by Git.\",\n            \"commands\": [\n                {\n                    \"cmd\": \"cat .gitignore\",\n                    \"missing_fields\": [],\n                    \"reasoning\": \"Reviewing the contents of the `.gitignore` file can help manage version control.\"\n                }\n            ]\n        }\n    ]\n}"},"done_reason":"stop","total_duration":130625288958,"load_duration":28920875,"prompt_eval_count":1834,"prompt_eval_duration":20631456000,"eval_count":602,"eval_duration":109943901000}
     // "#;
 
-    // check out iter mut
-    // println!("enter you choice");
-    // for (i, suggestion) in suggestions.iter().enumerate() {
-    //     println!("{} - {} \n{}", i, suggestion.cmd, suggestion.reasoning);
-    // }
-    //
-    // let mut user_choice_str = String::new();
-    // _ = stdout().flush().expect("failed to flush stdout");
-    // _ = stdin()
-    //     .read_line(&mut user_choice_str)
-    //     .expect("error in getting user input");
-    //
-    // debug!("user entered {}", user_choice_str.trim());
-    //
-    // // bubble up err to user
-    // let user_choice_idx_res = validate_and_get_user_input_as_int(&suggestions, user_choice_str);
-    // if user_choice_idx_res.is_err() {
-    //     panic!("{}", user_choice_idx_res.unwrap_err());
-    // }
-    //
-    // let user_choice = suggestions.get(user_choice_idx_res.unwrap()).unwrap();
-    // debug!("user selected the command {}", user_choice.cmd);
-    //
-    // let mut execute_str = user_choice.cmd.clone();
-    // if !user_choice.missing_fields.is_empty() {
-    //     execute_str = get_missing_params_from_user(execute_str, user_choice.missing_fields.clone());
-    // }
-    //
-    // println!("cmd to exec is {}", execute_str);
-    //
-    // execute_str.split("&&").for_each(|mut cmd| {
-    //     cmd = cmd.trim();
-    //     println!("executing cmd {}", cmd);
-    //     let split_cmd = shell_words::split(&cmd).unwrap_or_else(|e| panic!("{}", e));
-    //     let (name, args) = split_cmd
-    //         .split_first()
-    //         .unwrap_or_else(|| panic!("error in getting command"));
-    //
-    //     let mut command = std::process::Command::new(name);
-    //     for arg in args {
-    //         command.arg(arg);
-    //     }
-    //
-    //     command.status().expect("command failed");
-    // });
+    select_and_execute(&matcher, &user_query, &suggestions);
+}
+
+fn get_user_query(matcher: &ArgMatches) -> Option<String> {
+    matcher
+        .try_get_many::<String>("input")
+        .unwrap_or_else(|err| panic!("{}", err))
+        .map(|words| words.cloned().collect::<Vec<String>>().join(" "))
+}
+
+fn get_snippet_suggestions(user_query: &str) -> Vec<ModelSuggestion> {
+    Snippets::get_matching(user_query, &Snippets::load())
+        .iter()
+        .map(Snippets::to_suggestion)
+        .collect()
+}
+
+// past picks for similar queries, they go into the prompt as examples next to the snippets
+fn get_examples(user_query: &str, context: &Context) -> Vec<Choice> {
+    Feedback::get_examples(user_query, &context.cwd, &Feedback::load())
+}
+
+fn print_suggestions(suggestions: &[ModelSuggestion]) {
+    if suggestions.is_empty() {
+        println!("no suggestions");
+        return;
+    }
+
+    // only needed for install hints, so it is only looked at when something is missing
+    let mut environment: Option<Environment> = None;
+
+    for (i, suggestion) in suggestions.iter().enumerate() {
+        println!("{} - {}", i, suggestion.reasoning);
+        for command in &suggestion.commands {
+            println!("    {}", command.cmd);
+            for binary in Verify::get_missing_binaries(&command.cmd) {
+                let environment = environment.get_or_insert_with(Environment::detect);
+                match Verify::get_install_hint(&binary, environment) {
+                    Some(hint) => println!("      ! {} is not installed, try `{}`", binary, hint),
+                    None => println!("      ! {} is not installed", binary),
+                }
+            }
+        }
+    }
+}
+
+// every follow up is sent along with the whole transcript so far, so the model can revise its
+// previous answer instead of starting over
+fn run_chat(
+    matcher: &ArgMatches,
+    session: &mut Session,
+    first_follow_up: Option<String>,
+    ctx: &Context,
+) {
+    let mut follow_up = first_follow_up;
+    loop {
+        let query = match follow_up.take() {
+            Some(query) => query,
+            None => {
+                print!("refine (empty to stop) -> ");
+                stdout().flush().expect("failed to flush stdout");
+                let mut line = String::new();
+                stdin()
+                    .read_line(&mut line)
+                    .expect("error in getting user input");
+                line
+            }
+        };
+        if query.trim().is_empty() {
+            break;
+        }
+
+        session.push("user", query.trim().to_string());
+        let request = OllamaRequest {
+            model: session.model.clone(),
+            format: "json".to_string(),
+            stream: false,
+            messages: session.messages.clone(),
+        };
+
+        let response = match get_model_response(matcher, &request, ctx) {
+            Some(response) => response,
+            None => {
+                println!("the model is not available, can't refine the suggestions");
+                session.messages.pop();
+                break;
+            }
+        };
+
+        session.push(
+            "assistant",
+            to_string(&response).unwrap_or_else(|e| panic!("{}", e)),
+        );
+        session.save();
+        print_suggestions(&response.response);
+    }
+
+    println!("continue this session with --resume {}", session.id);
+}
+
+// None when the backend can't be reached, so the caller can fall back to searching the history,
+// otherwise the suggestions come back deduplicated and ranked
+fn get_model_response(
+    matcher: &ArgMatches,
+    request: &OllamaRequest,
+    ctx: &Context,
+) -> Option<OllamaPlaceholderResponse> {
+    let response = match get_parsed_model_response(matcher, request) {
+        Ok(response) => response,
+        Err(BackendError::Unavailable(_)) => return None,
+        Err(e) => exit_with_error(&e),
+    };
+
+    // flags that don't exist are sent back once with a report, if the model can't be reached for
+    // that the first answer is used as is
+    let invalid = match matcher.get_flag("no-verify") {
+        true => vec![],
+        false => Verify::get_invalid_flags(&response.response),
+    };
+    let response = match invalid.is_empty() {
+        true => response,
+        false => {
+            debug!("asking again, {} invalid flags", invalid.len());
+            let mut retry = request.clone();
+            retry.messages.push(OllamaMessage {
+                role: "assistant".to_string(),
+                content: to_string(&response).unwrap_or_else(|e| panic!("{}", e)),
+            });
+            retry.messages.push(OllamaMessage {
+                role: "user".to_string(),
+                content: Verify::get_reprompt_message(&invalid),
+            });
+            get_parsed_model_response(matcher, &retry).unwrap_or(response)
+        }
+    };
+
+    let mut suggestions = Ranking::process(response.response, ctx);
+    add_context_suggestions(&mut suggestions, ctx);
+    Some(OllamaPlaceholderResponse {
+        response: suggestions,
+    })
+}
+
+// missing fields get the values the context knows about, like the hosts from the ssh config or
+// the running containers
+fn add_context_suggestions(suggestions: &mut [ModelSuggestion], ctx: &Context) {
+    SshProvider::add_host_suggestions(suggestions, ctx);
+    KubernetesProvider::add_suggestions(suggestions, ctx);
+    DockerProvider::add_suggestions(suggestions, ctx);
+}
+
+fn get_parsed_model_response(
+    matcher: &ArgMatches,
+    request: &OllamaRequest,
+) -> Result<OllamaPlaceholderResponse, BackendError> {
+    let content = get_model_content(matcher, request)?;
+
+    // the model sometimes gives up and returns nothing at all, that's not worth an error
+    if content.trim().is_empty() {
+        warn!("model returned an empty response");
+        return Ok(OllamaPlaceholderResponse { response: vec![] });
+    }
+    from_str::<OllamaPlaceholderResponse>(&content)
+        .map_err(|e| BackendError::Malformed(e.to_string()))
+}
+
+// sends the request and hands back what the model said, also takes care of the usage stats
+// secrets never leave the machine, they are swapped for placeholders here and put back into the
+// answer
+fn get_model_content(
+    matcher: &ArgMatches,
+    request: &OllamaRequest,
+) -> Result<String, BackendError> {
+    let mut redactor = Redactor::load();
+    let request = redactor.redact_request(request);
+    if matcher.get_flag("show-context") {
+        for message in &request.messages {
+            println!("{}:\n{}\n", message.role, message.content);
+        }
+    }
+
+    let backend = get_backend(matcher);
+    let response_text = backend
+        .get_response(&request)
+        .inspect_err(|e| warn!("{}", e))?;
+    trace!("raw response is {}", response_text);
+
+    let response = from_str::<OllamaResponse>(&response_text)
+        .map_err(|e| BackendError::Malformed(e.to_string()))?;
+    debug!(
+        "response is {}",
+        to_string(&response).unwrap_or("unable to deserialize response".to_string())
+    );
+
+    let usage = Usage::from_response(&response, &Prompts::get_version());
+    if backend.is_real() {
+        Stats::save(&usage);
+    }
+    if matcher.get_flag("stats") {
+        Stats::print_usage(&usage);
+    }
+
+    Ok(redactor.restore(&response.message.content))
+}
+
+// a model that answers with something else than the schema is a dead end, there is nothing to
+// fall back on like there is when it can't be reached
+fn exit_with_error(e: &BackendError) -> ! {
+    eprintln!("{}", e);
+    std::process::exit(1)
+}
+
+fn run_fix(matcher: &ArgMatches, sub_matcher: &ArgMatches) {
+    let history_file_path = matcher
+        .get_one::<String>("history")
+        .cloned()
+        .unwrap_or_default();
+    let context = init_and_get_context(history_file_path, "");
+
+    let mut last_command =
+        match LastCommand::from_hook().or_else(|| LastCommand::from_history(&context)) {
+            Some(last_command) => last_command,
+            None => {
+                println!("there is no previous command to fix");
+                return;
+            }
+        };
+
+    // the error output only comes from re-running, which can have side effects, so it's never
+    // done without asking or --rerun
+    let rerun = sub_matcher.get_flag("rerun") || {
+        print!(
+            "re-run `{}` to capture its error output? [y/N] -> ",
+            last_command.cmd
+        );
+        stdout().flush().expect("failed to flush stdout");
+        let mut answer = String::new();
+        _ = stdin()
+            .read_line(&mut answer)
+            .expect("error in getting user input");
+        answer.trim().eq_ignore_ascii_case("y")
+    };
+    if rerun {
+        last_command.rerun();
+    }
+
+    if last_command.exit_status == Some(0) {
+        println!(
+            "`{}` did not fail, asking for a fix anyway",
+            last_command.cmd
+        );
+    }
+
+    let request = OllamaRequest {
+        model: MODEL.to_string(),
+        format: "json".to_string(),
+        stream: false,
+        messages: vec![
+            OllamaMessage {
+                role: "system".to_string(),
+                content: Prompts::get_system_prompt(&context, &[], &[]),
+            },
+            OllamaMessage {
+                role: "user".to_string(),
+                content: last_command.get_user_message(),
+            },
+        ],
+    };
+    trace!("fix request is {}", request.messages[1].content);
+
+    let suggestions = match get_model_response(matcher, &request, &context) {
+        Some(response) => response.response,
+        None => {
+            println!("the model is not available, can't fix the command");
+            return;
+        }
+    };
+
+    print_suggestions(&suggestions);
+    select_and_execute(matcher, &format!("fix {}", last_command.cmd), &suggestions);
+}
+
+fn run_prompt(matcher: &ArgMatches, sub_matcher: &ArgMatches) {
+    if sub_matcher.get_flag("init") {
+        let written = Prompts::init();
+        for path in &written {
+            println!("wrote {}", path.display());
+        }
+        if written.is_empty() {
+            println!("the prompt templates are already in place");
+        }
+        return;
+    }
+
+    let user_query = sub_matcher
+        .get_many::<String>("query")
+        .map(|query| query.cloned().collect::<Vec<String>>().join(" "))
+        .unwrap_or_else(|| panic!("no query to render the prompt for"));
+    let history_file_path = matcher
+        .get_one::<String>("history")
+        .cloned()
+        .unwrap_or_default();
+    let context = init_and_get_context(history_file_path, &user_query);
+    let snippet_suggestions = get_snippet_suggestions(&user_query);
+    let examples = get_examples(&user_query, &context);
+    let mut redactor = Redactor::load();
+
+    println!("prompt version {}", Prompts::get_version());
+    println!();
+    println!("system:");
+    println!(
+        "{}",
+        redactor.redact_prompt(&Prompts::get_system_prompt(
+            &context,
+            &snippet_suggestions,
+            &examples
+        ))
+    );
+    println!();
+    println!("user:");
+    println!("{}", redactor.redact(&user_query));
+}
+
+fn run_explain(matcher: &ArgMatches, cmd: &str) {
+    let request = OllamaRequest {
+        model: MODEL.to_string(),
+        format: "json".to_string(),
+        stream: false,
+        messages: vec![
+            OllamaMessage {
+                role: "system".to_string(),
+                content: Prompts::get_explain_prompt(),
+            },
+            OllamaMessage {
+                role: "user".to_string(),
+                content: Explain::get_user_message(cmd),
+            },
+        ],
+    };
+
+    let content = match get_model_content(matcher, &request) {
+        Ok(content) => content,
+        Err(BackendError::Unavailable(_)) => {
+            println!("the model is not available, can't explain the command");
+            return;
+        }
+        Err(e) => exit_with_error(&e),
+    };
+
+    let explanation = match content.trim().is_empty() {
+        true => {
+            warn!("model returned an empty response");
+            ExplainResponse { stages: vec![] }
+        }
+        false => from_str::<ExplainResponse>(&content)
+            .unwrap_or_else(|e| exit_with_error(&BackendError::Malformed(e.to_string()))),
+    };
+    Explain::print_explanation(&explanation);
+}
+
+fn get_backend(matcher: &ArgMatches) -> Box<dyn Backend> {
+    if let Some(dir) = matcher.get_one::<String>("replay") {
+        debug!("replaying responses from {}", dir);
+        return Box::new(ReplayBackend {
+            dir: dir.to_string(),
+        });
+    }
+
+    let backend: Box<dyn Backend> = match matcher.get_one::<String>("backend").map(|s| s.as_str()) {
+        Some("ollama") => Box::new(OllamaBackend {
+            url: matcher
+                .get_one::<String>("url")
+                .cloned()
+                .unwrap_or_default(),
+        }),
+        _ => Box::new(DummyBackend),
+    };
+
+    match matcher.get_one::<String>("record") {
+        Some(dir) => {
+            debug!("recording responses to {}", dir);
+            Box::new(RecordingBackend {
+                inner: backend,
+                dir: dir.to_string(),
+            })
+        }
+        None => backend,
+    }
+}
+
+// asks which suggestion to go with, fills in its missing fields and runs its commands one after
+// the other, stopping at the first one that fails
+// what was picked or skipped is saved along with the query, see Feedback
+fn select_and_execute(matcher: &ArgMatches, query: &str, suggestions: &[ModelSuggestion]) {
+    if suggestions.is_empty() {
+        return;
+    }
+
+    print!("enter your choice, or save <name> (empty to skip) -> ");
+    stdout().flush().expect("failed to flush stdout");
+    let mut user_choice_str = String::new();
+    _ = stdin()
+        .read_line(&mut user_choice_str)
+        .expect("error in getting user input");
+
+    debug!("user entered {}", user_choice_str.trim());
+    if user_choice_str.trim().is_empty() {
+        Feedback::save(&Choice::new(query, suggestions, None, vec![]));
+        return;
+    }
+
+    if let Some(name) = user_choice_str.trim().strip_prefix("save ") {
+        save_alias(name.trim(), suggestions);
+        return;
+    }
+
+    let idx = match validate_and_get_user_input_as_int(suggestions, user_choice_str) {
+        Ok(idx) => idx,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let user_choice = &suggestions[idx];
+    debug!("user selected the suggestion {}", user_choice.reasoning);
+
+    let mut values = vec![];
+    match matcher.get_one::<String>("emit-script") {
+        Some(path) => {
+            Script::write(path, user_choice);
+            println!("wrote script to {}", path);
+        }
+        None => execute_suggestion(user_choice, &mut values),
+    }
+    Feedback::save(&Choice::new(query, suggestions, Some(idx), values));
+}
+
+fn execute_suggestion(suggestion: &ModelSuggestion, values: &mut Vec<FieldValue>) {
+    for command in &suggestion.commands {
+        let mut execute_str = command.cmd.clone();
+        if !command.missing_fields.is_empty() {
+            execute_str =
+                get_missing_params_from_user(execute_str, command.missing_fields.clone(), values);
+        }
+
+        if !Safety::get_warnings(&execute_str).is_empty() && !confirm_command(&execute_str) {
+            println!("not running the rest");
+            return;
+        }
+
+        println!("executing cmd {}", execute_str);
+        let status = execute_command(&execute_str);
+        if !status.success() {
+            println!("command failed with {}, not running the rest", status);
+            return;
+        }
+    }
 }
 
-fn get_missing_params_from_user(mut cmd: String, missing_fields: Vec<MissingField>) -> String {
+// the agent keeps going on its own, so every step is confirmed and anything risky needs a full yes
+fn run_agent(matcher: &ArgMatches, user_query: String, context: &Context) {
+    let max_steps = *matcher.get_one::<usize>("max-steps").unwrap_or(&10);
+    let mut request = OllamaRequest {
+        model: MODEL.to_string(),
+        format: "json".to_string(),
+        stream: false,
+        messages: vec![
+            OllamaMessage {
+                role: "system".to_string(),
+                content: Prompts::get_agent_prompt(context),
+            },
+            OllamaMessage {
+                role: "user".to_string(),
+                content: user_query,
+            },
+        ],
+    };
+
+    for step in 1..=max_steps {
+        let content = match get_model_content(matcher, &request) {
+            Ok(content) => content,
+            Err(BackendError::Unavailable(_)) => {
+                println!("the model is not available, can't run the agent");
+                return;
+            }
+            Err(e) => exit_with_error(&e),
+        };
+        let response = from_str::<AgentResponse>(&content)
+            .unwrap_or_else(|e| exit_with_error(&BackendError::Malformed(e.to_string())));
+
+        let command = match (response.done, response.command) {
+            (false, Some(command)) => command,
+            _ => {
+                println!("done - {}", response.reasoning);
+                return;
+            }
+        };
+        println!("step {} - {}", step, response.reasoning);
+        println!("    {}", command.cmd);
+
+        let mut cmd = command.cmd.clone();
+        if !command.missing_fields.is_empty() {
+            cmd = get_missing_params_from_user(cmd, command.missing_fields.clone(), &mut vec![]);
+        }
+        if !confirm_command(&cmd) {
+            println!("stopping the agent");
+            return;
+        }
+
+        let output = StepOutput::run(&cmd);
+        print!("{}", output.stdout);
+        eprint!("{}", output.stderr);
+
+        request.messages.push(OllamaMessage {
+            role: "assistant".to_string(),
+            content,
+        });
+        request.messages.push(OllamaMessage {
+            role: "user".to_string(),
+            content: output.get_user_message(&cmd),
+        });
+    }
+
+    println!("stopped after {} steps", max_steps);
+}
+
+// a plain y is enough for normal commands, anything the safety checks flag has to be typed out
+fn confirm_command(cmd: &str) -> bool {
+    let warnings = Safety::get_warnings(cmd);
+    for warning in &warnings {
+        println!("warning: `{}` {}", cmd, warning);
+    }
+
+    match warnings.is_empty() {
+        true => print!("run `{}`? [y/N] -> ", cmd),
+        false => print!("type yes to run it anyway -> "),
+    }
+    stdout().flush().expect("failed to flush stdout");
+    let mut answer = String::new();
+    _ = stdin()
+        .read_line(&mut answer)
+        .expect("error in getting user input");
+
+    let confirmed = match warnings.is_empty() {
+        true => ["y", "yes"].contains(&answer.trim().to_lowercase().as_str()),
+        false => answer.trim() == "yes",
+    };
+    debug!("user confirmed {} -> {}", cmd, confirmed);
+    confirmed
+}
+
+// with more than one suggestion the user is asked which one to save
+fn save_alias(name: &str, suggestions: &[ModelSuggestion]) {
+    if !Alias::is_valid_name(name) {
+        println!(
+            "{} can't be used as a name, use letters, digits, _ and - only",
+            name
+        );
+        return;
+    }
+    if Alias::is_taken(name) {
+        println!(
+            "{} is already a command or shell builtin, pick another name",
+            name
+        );
+        return;
+    }
+
+    let suggestion = match suggestions.len() {
+        1 => &suggestions[0],
+        _ => {
+            print!("which suggestion should be saved as {} -> ", name);
+            stdout().flush().expect("failed to flush stdout");
+            let mut choice = String::new();
+            _ = stdin()
+                .read_line(&mut choice)
+                .expect("error in getting user input");
+            match validate_and_get_user_input_as_int(suggestions, choice) {
+                Ok(idx) => &suggestions[idx],
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            }
+        }
+    };
+
+    Alias::add(name, suggestion);
+    println!(
+        "saved {}, it is available as a shell function the next time the hook is loaded",
+        name
+    );
+}
+
+fn run_history(sub_matcher: &ArgMatches) {
+    // adding doesn't need the index, it runs after every command so it only appends to the log
+    if let Some(("add", add_matcher)) = sub_matcher.subcommand() {
+        let cmd = add_matcher
+            .get_one::<String>("cmd")
+            .unwrap_or_else(|| panic!("no command to add"));
+        let dir = match add_matcher.get_one::<String>("dir") {
+            Some(dir) => dir.clone(),
+            None => env::current_dir()
+                .unwrap_or_else(|e| panic!("{}", e))
+                .to_string_lossy()
+                .to_string(),
+        };
+        HistoryStore::add(&History {
+            dir,
+            cmd: cmd.clone(),
+            datetime: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        });
+        return;
+    }
+
+    let mut store = HistoryStore::open();
+    match sub_matcher.subcommand() {
+        Some(("import", import_matcher)) => {
+            let file = import_matcher
+                .get_one::<String>("file")
+                .unwrap_or_else(|| panic!("no file to import"));
+            match store.import(Path::new(file)) {
+                Ok((imported, skipped)) => println!(
+                    "imported {} records, skipped {} broken ones, {} in the store",
+                    imported,
+                    skipped,
+                    store.len()
+                ),
+                Err(e) => println!("unable to import {}", e),
+            }
+        }
+        Some(("list", list_matcher)) => {
+            let get = |name: &str| list_matcher.get_one::<String>(name).cloned();
+            let ids = store.find(&HistoryQuery {
+                dir: get("dir"),
+                since: get("since"),
+                until: get("until"),
+                prefix: get("prefix"),
+            });
+            if list_matcher.get_flag("counts") {
+                for (cmd, count) in store.get_counts(&ids) {
+                    println!("{:>5}  {}", count, cmd);
+                }
+                return;
+            }
+            for history in store.get(&ids) {
+                println!("{}  {}  {}", history.datetime, history.dir, history.cmd);
+            }
+        }
+        _ => {}
+    }
+}
+
+// the only place the history gets embedded, a query only embeds itself
+fn run_index(matcher: &ArgMatches, rebuild: bool) {
+    let history_file_path = matcher
+        .get_one::<String>("history")
+        .cloned()
+        .unwrap_or_default();
+    let history = HistoryProvider::read(&history_file_path).unwrap_or_default();
+    let options = ContextConfig::load().get_options::<IndexOptions>("similar");
+
+    let indexed = match rebuild {
+        true => EmbeddingIndex::rebuild(&options, &history),
+        false => match EmbeddingIndex::load(&options) {
+            Some(mut index) => index.update(&history),
+            None => {
+                println!("there is no index to update yet, build it with `index rebuild`");
+                return;
+            }
+        },
+    };
+    match indexed {
+        Ok(count) => println!("indexed {} commands with {}", count, options.model),
+        Err(e) => println!("unable to embed the history with {}: {}", options.url, e),
+    }
+}
+
+fn run_aliases(sub_matcher: &ArgMatches) {
+    match sub_matcher.subcommand() {
+        Some(("remove", remove_matcher)) => {
+            let name = remove_matcher
+                .get_one::<String>("name")
+                .unwrap_or_else(|| panic!("no alias name given"));
+            match Alias::remove(name) {
+                true => println!("removed {}", name),
+                false => println!("there is no alias named {}", name),
+            }
+        }
+        Some(("export", export_matcher)) => {
+            let aliases = Alias::load_all();
+            match export_matcher
+                .get_one::<String>("format")
+                .map(|f| f.as_str())
+            {
+                Some("json") => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&aliases).unwrap_or_else(|e| panic!("{}", e))
+                ),
+                _ => print!("{}", Alias::get_functions(&aliases)),
+            }
+        }
+        _ => Alias::print_list(&Alias::load_all()),
+    }
+}
+
+// commands can chain, pipe and redirect, so they go through a shell instead of being split up
+fn execute_command(cmd: &str) -> ExitStatus {
+    std::process::Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .status()
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+// the values the user ends up with are added to values, to be saved with the choice
+fn get_missing_params_from_user(
+    mut cmd: String,
+    missing_fields: Vec<MissingField>,
+    values: &mut Vec<FieldValue>,
+) -> String {
     debug!("start getting user input for command");
+    let choices = Feedback::load();
     for field in missing_fields {
+        let field = Feedback::rank_field(&field, &choices);
+        println!("{}", field.reasoning);
+        for (i, suggestion) in field.suggestions.iter().enumerate() {
+            println!("  {} - {} ({})", i, suggestion.value, suggestion.reasoning);
+        }
+
         let mut value = String::new();
-        _ = stdout().flush().expect("failed to flush stdout");
-        println!("Enter the value for {} -> ", field.key);
+        match field.suggestions.is_empty() {
+            true => print!("Enter the value for {} -> ", field.key),
+            false => print!(
+                "Enter the value for {}, or the number of a suggestion -> ",
+                field.key
+            ),
+        }
+        stdout().flush().expect("failed to flush stdout");
         _ = stdin()
             .read_line(&mut value)
             .expect("error in getting user input");
 
+        // a number picks one of the suggestions, anything else is taken as is
+        let value = match value.trim().parse::<usize>() {
+            Ok(i) if i < field.suggestions.len() => field.suggestions[i].value.clone(),
+            _ => value.trim().to_string(),
+        };
+
         let pattern = format!("<{}>", field.key);
-        cmd = cmd.replace(pattern.as_str(), value.trim());
+        cmd = cmd.replace(pattern.as_str(), &value);
+        values.push(FieldValue {
+            key: field.key.clone(),
+            value,
+        });
     }
 
-    return cmd;
+    cmd
 }
 
 fn validate_and_get_user_input_as_int(
-    suggestions: &Vec<ModelSuggestion>,
+    suggestions: &[ModelSuggestion],
     user_choice: String,
 ) -> Result<usize, CustomParserError> {
-    let parsed_val_res = user_choice.trim().parse::<u32>();
-
-    if parsed_val_res.is_err() {
-        return Err(CustomParserError::ParseIntError(
-            parsed_val_res.unwrap_err(),
-        ));
-    }
-
-    let parsed_val = parsed_val_res.unwrap();
+    let parsed_val = match user_choice.trim().parse::<u32>() {
+        Ok(val) => val,
+        Err(e) => return Err(CustomParserError::ParseIntError(e)),
+    };
 
     let suggestion = suggestions.get(parsed_val as usize);
-    if let None = suggestion {
+    if suggestion.is_none() {
         return Err(CustomParserError::OutOfBoundError(format!(
             "{} is out of bounds",
             parsed_val
@@ -224,21 +1346,12 @@ fn set_log_level(matcher: &ArgMatches) {
     env_logger::init();
 }
 
-fn init_and_get_context(his_file_path: String) -> Context {
-    let history_file_path = match his_file_path.is_empty() {
-        true => "/Users/shubham/.zli_history".to_string(),
-        false => his_file_path,
-    };
-
-    debug!("history file path is {}", history_file_path);
-
-    let mut buf = String::new();
-    fs::File::open(history_file_path)
-        .unwrap_or_else(|e| panic!("{}", e))
-        .read_to_string(&mut buf)
-        .unwrap_or_else(|e| panic!("{}", e));
-
-    let history: Vec<History> = serde_json::from_str(&buf).unwrap_or_else(|e| panic!("{}", e));
+// the query decides which files get excerpts, it is empty when there is none yet
+fn init_and_get_context(his_file_path: String, query: &str) -> Context {
+    // a history file that was asked for has to be there, the default one is optional
+    if !his_file_path.is_empty() {
+        fs::metadata(&his_file_path).unwrap_or_else(|e| panic!("{}: {}", his_file_path, e));
+    }
 
     let cwd_path_buf = env::current_dir().unwrap_or_else(|e| panic!("{}", e));
     let cwd = cwd_path_buf
@@ -246,24 +1359,5 @@ fn init_and_get_context(his_file_path: String) -> Context {
         .unwrap_or_else(|| panic!("could not get cwd"))
         .to_string();
 
-    let ls = cwd_path_buf
-        .read_dir()
-        .unwrap_or_else(|e| panic!("{}", e))
-        .map(|file| {
-            let f = file.unwrap_or_else(|e| panic!("{}", e));
-
-            let is_file = f.file_type().unwrap_or_else(|e| panic!("{}", e)).is_file();
-            let f_type = match is_file {
-                true => "file".to_string(),
-                false => "directory".to_string(),
-            };
-
-            File {
-                name: f.file_name().to_str().unwrap().to_string(),
-                kind: f_type.to_string(),
-            }
-        })
-        .collect();
-
-    return Context { cwd, ls, history };
+    ContextProviders::collect(ContextProviders::get_all(&his_file_path, query), &cwd)
 }
@@ -0,0 +1,302 @@
+use std::fs;
+use std::path::PathBuf;
+
+use log::debug;
+use minijinja::{context, Environment as Templates, UndefinedBehavior};
+use serde_json::to_string;
+
+use crate::feedback::Choice;
+use crate::models::*;
+use crate::storage::{get_data_dir, get_key};
+
+// the built in templates, a file with the same name in $ZLI_HOME/prompts takes precedence
+const DEFAULTS: [(&str, &str); 6] = [
+    ("system.j2", include_str!("../prompts/system.j2")),
+    (
+        "system.schema.json",
+        include_str!("../prompts/system.schema.json"),
+    ),
+    ("explain.j2", include_str!("../prompts/explain.j2")),
+    (
+        "explain.schema.json",
+        include_str!("../prompts/explain.schema.json"),
+    ),
+    ("agent.j2", include_str!("../prompts/agent.j2")),
+    (
+        "agent.schema.json",
+        include_str!("../prompts/agent.schema.json"),
+    ),
+];
+
+// data that goes into the prompt as is, after the instructions. templates from before the
+// sections existed placed it themselves through a variable of the same name, so a section whose
+// variable the template uses is left out
+struct Section {
+    title: &'static str,
+    variable: &'static str,
+    body: String,
+}
+
+pub struct Prompts;
+
+impl Prompts {
+    // bump this whenever the built in templates change, cached responses are keyed on it
+    pub const VERSION: &'static str = "10";
+
+    fn get_prompts_dir() -> PathBuf {
+        get_data_dir().join("prompts")
+    }
+
+    fn get_overrides() -> Vec<(&'static str, String)> {
+        DEFAULTS
+            .iter()
+            .filter_map(|(name, _)| {
+                let source = fs::read_to_string(Prompts::get_prompts_dir().join(name)).ok()?;
+                Some((*name, source))
+            })
+            .collect()
+    }
+
+    fn get_source(name: &str) -> String {
+        let path = Prompts::get_prompts_dir().join(name);
+        match fs::read_to_string(&path) {
+            Ok(source) => {
+                debug!("using prompt template {}", path.display());
+                source
+            }
+            Err(_) => DEFAULTS
+                .iter()
+                .find(|(default_name, _)| *default_name == name)
+                .map(|(_, source)| source.to_string())
+                .unwrap_or_else(|| panic!("no prompt template named {}", name)),
+        }
+    }
+
+    // edited templates get a version of their own, so their responses are never mixed up with the
+    // ones from the built in templates
+    pub fn get_version() -> String {
+        let overrides = Prompts::get_overrides();
+        if overrides.is_empty() {
+            return Prompts::VERSION.to_string();
+        }
+
+        let mut parts = vec![Prompts::VERSION];
+        for (name, source) in &overrides {
+            parts.push(name);
+            parts.push(source);
+        }
+        format!("{}-custom-{}", Prompts::VERSION, &get_key(&parts)[..8])
+    }
+
+    // the templates only hold the instructions, anything that is data (the schema, examples and
+    // the context) is appended as a section so its whitespace is kept exactly
+    fn render(name: &str, values: minijinja::Value, sections: Vec<Section>) -> String {
+        let mut templates = Templates::new();
+        templates.set_undefined_behavior(UndefinedBehavior::Strict);
+        templates
+            .add_template_owned(name.to_string(), Prompts::get_source(name))
+            .unwrap_or_else(|e| panic!("bad prompt template {}: {}", name, e));
+        let template = templates
+            .get_template(name)
+            .unwrap_or_else(|e| panic!("bad prompt template {}: {}", name, e));
+
+        let rendered = template
+            .render(values)
+            .unwrap_or_else(|e| panic!("unable to render prompt template {}: {}", name, e));
+
+        let used = template.undeclared_variables(false);
+        let sections = sections
+            .into_iter()
+            .filter(|section| !used.contains(section.variable))
+            .collect::<Vec<Section>>();
+        Prompts::assemble(Prompts::normalize_prose(&rendered), sections)
+    }
+
+    // every paragraph of prose ends up on a single line, the blank lines between paragraphs are
+    // kept and so are indented lines and list items, they hold examples that need their layout
+    fn normalize_prose(text: &str) -> String {
+        let mut paragraphs = vec![];
+        let mut lines: Vec<String> = vec![];
+        let mut current: Vec<&str> = vec![];
+        for line in text.lines() {
+            let verbatim = Prompts::is_verbatim(line);
+            if (line.trim().is_empty() || verbatim) && !current.is_empty() {
+                lines.push(current.join(" "));
+                current.clear();
+            }
+            if line.trim().is_empty() {
+                if !lines.is_empty() {
+                    paragraphs.push(lines.join("\n"));
+                    lines.clear();
+                }
+                continue;
+            }
+            if verbatim {
+                lines.push(line.trim_end().to_string());
+                continue;
+            }
+            current.extend(line.split_whitespace());
+        }
+        if !current.is_empty() {
+            lines.push(current.join(" "));
+        }
+        if !lines.is_empty() {
+            paragraphs.push(lines.join("\n"));
+        }
+
+        paragraphs.join("\n\n")
+    }
+
+    fn is_verbatim(line: &str) -> bool {
+        if line.starts_with(char::is_whitespace) {
+            return true;
+        }
+        let marker = line.trim_start_matches(|c: char| c.is_ascii_digit());
+        let numbered =
+            marker.len() < line.len() && (marker.starts_with(") ") || marker.starts_with(". "));
+        numbered || line.starts_with("- ") || line.starts_with("* ")
+    }
+
+    // empty sections are left out entirely
+    fn assemble(instructions: String, sections: Vec<Section>) -> String {
+        let mut prompt = instructions;
+        for section in sections.iter().filter(|section| !section.body.is_empty()) {
+            prompt.push_str(&format!("\n\n### {}\n{}", section.title, section.body));
+        }
+        prompt
+    }
+
+    // the schema is the example output the model has to follow, kept as json so it can be edited
+    // without worrying about escaping
+    fn get_schema(name: &str) -> Section {
+        Section {
+            title: "SCHEMA",
+            variable: "schema",
+            body: Prompts::get_source(&format!("{}.schema.json", name))
+                .trim()
+                .to_string(),
+        }
+    }
+
+    pub fn get_schemas() -> Vec<String> {
+        ["system", "explain", "agent"]
+            .iter()
+            .map(|name| Prompts::get_schema(name).body)
+            .collect()
+    }
+
+    fn get_context(ctx: &Context) -> Section {
+        Section {
+            title: "CONTEXT",
+            variable: "context",
+            body: to_string(&ctx.get_prompt_value()).unwrap_or_else(|e| panic!("{}", e)),
+        }
+    }
+
+    // snippets that match the query and past picks for similar queries are added as examples
+    pub fn get_system_prompt(
+        ctx: &Context,
+        snippets: &[ModelSuggestion],
+        examples: &[Choice],
+    ) -> String {
+        let examples = examples
+            .iter()
+            .filter_map(|choice| {
+                let picked = choice.picked.clone()?;
+                let output = OllamaPlaceholderResponse {
+                    response: vec![picked],
+                };
+                Some((
+                    choice.query.clone(),
+                    to_string(&output).unwrap_or_else(|e| panic!("{}", e)),
+                ))
+            })
+            .collect::<Vec<(String, String)>>();
+        let snippets = match snippets.is_empty() {
+            true => String::new(),
+            false => to_string(snippets).unwrap_or_else(|e| panic!("{}", e)),
+        };
+        let schema = Prompts::get_schema("system");
+        let context = Prompts::get_context(ctx);
+
+        Prompts::render(
+            "system.j2",
+            context! {
+                environment => ctx.environment.get_description(),
+                os => &ctx.environment,
+                has_snippets => !snippets.is_empty(),
+                has_examples => !examples.is_empty(),
+                schema => schema.body,
+                snippets => snippets,
+                examples => examples
+                    .iter()
+                    .map(|(query, output)| context! { query, output })
+                    .collect::<Vec<minijinja::Value>>(),
+                context => context.body,
+            },
+            vec![
+                schema,
+                Section {
+                    title: "SNIPPETS",
+                    variable: "snippets",
+                    body: snippets,
+                },
+                Section {
+                    title: "EXAMPLES",
+                    variable: "examples",
+                    body: examples
+                        .iter()
+                        .map(|(query, output)| format!("query: {}\noutput: {}", query, output))
+                        .collect::<Vec<String>>()
+                        .join("\n\n"),
+                },
+                context,
+            ],
+        )
+    }
+
+    // used by the explain subcommand, the command itself is sent as the user message
+    pub fn get_explain_prompt() -> String {
+        let schema = Prompts::get_schema("explain");
+        Prompts::render(
+            "explain.j2",
+            context! {
+                schema => schema.body,
+            },
+            vec![schema],
+        )
+    }
+
+    // used by the agent mode, the model plans one command at a time and gets to see its output
+    pub fn get_agent_prompt(ctx: &Context) -> String {
+        let schema = Prompts::get_schema("agent");
+        let context = Prompts::get_context(ctx);
+        Prompts::render(
+            "agent.j2",
+            context! {
+                environment => ctx.environment.get_description(),
+                os => &ctx.environment,
+                schema => schema.body,
+                context => context.body,
+            },
+            vec![schema, context],
+        )
+    }
+
+    // copies the built in templates to the prompts dir so they can be edited, existing files are
+    // left alone
+    pub fn init() -> Vec<PathBuf> {
+        let dir = Prompts::get_prompts_dir();
+        fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("{}", e));
+
+        DEFAULTS
+            .iter()
+            .map(|(name, source)| (dir.join(name), source))
+            .filter(|(path, _)| !path.exists())
+            .map(|(path, source)| {
+                fs::write(&path, source).unwrap_or_else(|e| panic!("{}", e));
+                path
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,248 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use log::{debug, warn};
+use serde_json::{json, to_value, Value};
+
+use crate::context::{ContextConfig, ContextProvider, ProviderSettings};
+use crate::environment::Environment;
+use crate::history_store::HistoryStore;
+use crate::models::*;
+use crate::storage::get_data_dir;
+
+// how many of the changed files and recent commits git shows
+const MAX_GIT_ENTRIES: usize = 20;
+
+// marker file, project type and the tool that goes with it
+const PROJECT_MARKERS: [(&str, &str, &str); 14] = [
+    ("Cargo.toml", "rust", "cargo"),
+    ("package.json", "node", "npm"),
+    ("pyproject.toml", "python", "pip"),
+    ("requirements.txt", "python", "pip"),
+    ("setup.py", "python", "pip"),
+    ("go.mod", "go", "go"),
+    ("pom.xml", "java", "mvn"),
+    ("build.gradle", "java", "gradle"),
+    ("build.gradle.kts", "kotlin", "gradle"),
+    ("Gemfile", "ruby", "bundle"),
+    ("composer.json", "php", "composer"),
+    ("CMakeLists.txt", "c++", "cmake"),
+    ("Makefile", "make", "make"),
+    ("Dockerfile", "docker", "docker"),
+];
+
+// lock files that say which node package manager is actually in use
+const NODE_LOCK_FILES: [(&str, &str); 3] = [
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "yarn"),
+    ("bun.lockb", "bun"),
+];
+
+// the file given with --history, or the history store or history.json in the data dir when there
+// is none
+pub struct HistoryProvider {
+    path: String,
+    settings: ProviderSettings,
+}
+
+impl HistoryProvider {
+    pub fn load(config: &ContextConfig, path: &str) -> HistoryProvider {
+        HistoryProvider {
+            path: path.to_string(),
+            settings: config.get_settings("history", 1000, 1000),
+        }
+    }
+
+    // the store once there is one, unless a file was asked for. the whole log is wanted, so it is
+    // read as it is, without opening the index
+    pub fn read(path: &str) -> Option<Vec<History>> {
+        if path.is_empty() && HistoryStore::exists() {
+            return Some(HistoryStore::read_all());
+        }
+
+        let path = match path.is_empty() {
+            true => get_data_dir().join("history.json"),
+            false => Path::new(path).to_path_buf(),
+        };
+        debug!("history file path is {}", path.display());
+
+        match fs::read_to_string(&path) {
+            Ok(buf) => Some(HistoryStore::parse_json(&buf).0),
+            Err(_) => {
+                warn!("no history at {}", path.display());
+                None
+            }
+        }
+    }
+}
+
+impl ContextProvider for HistoryProvider {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    // from the store only what's looked up for the cwd, a whole file is read as it is
+    fn collect(&self, cwd: &Path) -> Option<Value> {
+        if self.path.is_empty() && HistoryStore::exists() {
+            let history = HistoryStore::open().get_for_context(&cwd.to_string_lossy());
+            debug!("{} history records from the store", history.len());
+            return to_value(history).ok();
+        }
+        to_value(HistoryProvider::read(&self.path)?).ok()
+    }
+}
+
+// the branch, what changed and the last few commits of the repo the cwd is in, oldest first like
+// the history so trimming to the token budget keeps the newest
+pub struct GitProvider {
+    settings: ProviderSettings,
+}
+
+impl GitProvider {
+    pub fn load(config: &ContextConfig) -> GitProvider {
+        GitProvider {
+            settings: config.get_settings("git", 1000, 300),
+        }
+    }
+
+    fn get_output(cwd: &Path, args: &[&str]) -> Option<String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(cwd)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        match output.status.success() {
+            true => Some(
+                String::from_utf8_lossy(&output.stdout)
+                    .trim_end()
+                    .to_string(),
+            ),
+            false => None,
+        }
+    }
+
+    fn get_lines(cwd: &Path, args: &[&str]) -> Vec<String> {
+        GitProvider::get_output(cwd, args)
+            .unwrap_or_default()
+            .lines()
+            .take(MAX_GIT_ENTRIES)
+            .map(|line| line.to_string())
+            .collect()
+    }
+}
+
+impl ContextProvider for GitProvider {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, cwd: &Path) -> Option<Value> {
+        let root = GitProvider::get_output(cwd, &["rev-parse", "--show-toplevel"])?;
+
+        Some(json!({
+            "root": root,
+            "branch": GitProvider::get_output(cwd, &["rev-parse", "--abbrev-ref", "HEAD"]),
+            "remotes": GitProvider::get_lines(cwd, &["remote"]),
+            "changes": GitProvider::get_lines(cwd, &["status", "--porcelain"]),
+            "recent_commits": GitProvider::get_lines(
+                cwd,
+                &["log", "--oneline", "--reverse", &format!("-{}", MAX_GIT_ENTRIES)]
+            ),
+        }))
+    }
+}
+
+// what kind of project the cwd belongs to, going by the marker files of the closest dir that has
+// any
+pub struct ProjectProvider {
+    settings: ProviderSettings,
+}
+
+impl ProjectProvider {
+    pub fn load(config: &ContextConfig) -> ProjectProvider {
+        ProjectProvider {
+            settings: config.get_settings("project", 1000, 200),
+        }
+    }
+
+    fn get_tool(dir: &Path, kind: &str, tool: &str) -> String {
+        if kind != "node" {
+            return tool.to_string();
+        }
+        NODE_LOCK_FILES
+            .iter()
+            .find(|(lock_file, _)| dir.join(lock_file).is_file())
+            .map(|(_, tool)| tool.to_string())
+            .unwrap_or_else(|| tool.to_string())
+    }
+}
+
+impl ContextProvider for ProjectProvider {
+    fn name(&self) -> &str {
+        "project"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, cwd: &Path) -> Option<Value> {
+        cwd.ancestors().find_map(|dir| {
+            let mut types = vec![];
+            for (marker, kind, tool) in PROJECT_MARKERS {
+                if dir.join(marker).is_file() && !types.iter().any(|t: &Value| t["type"] == kind) {
+                    types.push(json!({
+                        "type": kind,
+                        "manifest": marker,
+                        "tool": ProjectProvider::get_tool(dir, kind, tool),
+                    }));
+                }
+            }
+
+            match types.is_empty() {
+                true => None,
+                false => Some(json!({
+                    "root": dir.to_string_lossy(),
+                    "types": types,
+                })),
+            }
+        })
+    }
+}
+
+pub struct EnvironmentProvider {
+    settings: ProviderSettings,
+}
+
+impl EnvironmentProvider {
+    pub fn load(config: &ContextConfig) -> EnvironmentProvider {
+        EnvironmentProvider {
+            settings: config.get_settings("environment", 2000, 150),
+        }
+    }
+}
+
+impl ContextProvider for EnvironmentProvider {
+    fn name(&self) -> &str {
+        "environment"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, _cwd: &Path) -> Option<Value> {
+        to_value(Environment::detect()).ok()
+    }
+}
@@ -0,0 +1,164 @@
+use log::debug;
+
+use crate::models::*;
+use crate::safety::Safety;
+use crate::verify::Verify;
+
+// the prompt asks for up to 5, that's also all that is shown
+const MAX_SUGGESTIONS: usize = 5;
+
+pub struct Ranking;
+
+impl Ranking {
+    // drops empty commands, merges suggestions that only differ in their placeholder names, and
+    // sorts what is left so the most likely to work come first. ties keep the models order
+    pub fn process(suggestions: Vec<ModelSuggestion>, ctx: &Context) -> Vec<ModelSuggestion> {
+        let mut merged: Vec<(String, ModelSuggestion)> = vec![];
+        for mut suggestion in suggestions {
+            suggestion
+                .commands
+                .retain(|command| !command.cmd.trim().is_empty());
+            if suggestion.commands.is_empty() {
+                debug!(
+                    "dropping suggestion without commands {}",
+                    suggestion.reasoning
+                );
+                continue;
+            }
+
+            let key = Ranking::get_normalized_key(&suggestion);
+            match merged.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, existing)) => {
+                    debug!("merging duplicate suggestion {}", suggestion.reasoning);
+                    Ranking::merge_fields(existing, &suggestion);
+                }
+                None => merged.push((key, suggestion)),
+            }
+        }
+
+        let mut scored = merged
+            .into_iter()
+            .map(|(_, suggestion)| (Ranking::get_score(&suggestion, ctx), suggestion))
+            .collect::<Vec<(f64, ModelSuggestion)>>();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        scored
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, suggestion)| suggestion)
+            .collect()
+    }
+
+    // every <placeholder> reads the same and whitespace is collapsed
+    fn get_normalized_cmd(cmd: &str) -> String {
+        let mut normalized = String::new();
+        let mut in_placeholder = false;
+        for c in cmd.chars() {
+            match c {
+                '<' => {
+                    in_placeholder = true;
+                    normalized.push_str("<>");
+                }
+                '>' if in_placeholder => in_placeholder = false,
+                _ if in_placeholder => {}
+                _ => normalized.push(c),
+            }
+        }
+        // an unclosed < was a redirect, not a placeholder
+        if in_placeholder {
+            return cmd.split_whitespace().collect::<Vec<&str>>().join(" ");
+        }
+
+        normalized
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    fn get_normalized_key(suggestion: &ModelSuggestion) -> String {
+        suggestion
+            .commands
+            .iter()
+            .map(|command| Ranking::get_normalized_cmd(&command.cmd))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // fields line up by position since the commands are the same apart from the names
+    fn merge_fields(existing: &mut ModelSuggestion, duplicate: &ModelSuggestion) {
+        for (command, other) in existing.commands.iter_mut().zip(&duplicate.commands) {
+            for (field, other_field) in command.missing_fields.iter_mut().zip(&other.missing_fields)
+            {
+                for suggestion in &other_field.suggestions {
+                    if !field
+                        .suggestions
+                        .iter()
+                        .any(|s| s.value == suggestion.value)
+                    {
+                        field.suggestions.push(suggestion.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    // how many history entries run the same program with the same first argument
+    fn get_history_count(cmd: &str, ctx: &Context) -> usize {
+        let prefix = cmd
+            .split_whitespace()
+            .take(2)
+            .filter(|word| !word.starts_with('<'))
+            .collect::<Vec<&str>>();
+        if prefix.is_empty() {
+            return 0;
+        }
+
+        ctx.history
+            .iter()
+            .filter(|entry| {
+                entry
+                    .cmd
+                    .split_whitespace()
+                    .take(prefix.len())
+                    .eq(prefix.iter().copied())
+            })
+            .count()
+    }
+
+    fn get_score(suggestion: &ModelSuggestion, ctx: &Context) -> f64 {
+        let cmds = suggestion
+            .commands
+            .iter()
+            .map(|command| command.cmd.as_str())
+            .collect::<Vec<&str>>();
+
+        let missing = cmds
+            .iter()
+            .flat_map(|cmd| Verify::get_binaries(cmd))
+            .filter(|binary| !Verify::binary_exists(binary))
+            .collect::<Vec<String>>();
+        let exists_weight = match missing.is_empty() {
+            true => 1.0,
+            false => 0.5,
+        };
+
+        let history_count: usize = cmds
+            .iter()
+            .map(|cmd| Ranking::get_history_count(cmd, ctx))
+            .sum();
+        let history_weight = 1.0 + (1.0 + history_count as f64).ln() * 0.1;
+
+        let risky = cmds.iter().any(|cmd| !Safety::get_warnings(cmd).is_empty());
+        let risk_weight = match risky {
+            true => 0.7,
+            false => 1.0,
+        };
+
+        let score = exists_weight * history_weight * risk_weight;
+        debug!(
+            "score {:.3} for {} (missing binaries {:?}, {} in history, risky {})",
+            score, suggestion.reasoning, missing, history_count, risky
+        );
+        score
+    }
+}
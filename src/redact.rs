@@ -0,0 +1,259 @@
+use std::fs;
+
+use log::{debug, warn};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::to_string;
+
+use crate::models::*;
+use crate::prompts::Prompts;
+use crate::storage::get_data_dir;
+
+// (kind, pattern), when the pattern has a group only that part is replaced so the prompt still
+// shows what kind of command it was
+const BUILTIN_RULES: [(&str, &str); 13] = [
+    (
+        "private_key",
+        r"-----BEGIN [A-Z ]*PRIVATE KEY-----(?s:.*?)-----END [A-Z ]*PRIVATE KEY-----",
+    ),
+    ("token", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+    ("token", r"\bgithub_pat_[A-Za-z0-9_]{22,}\b"),
+    ("token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}"),
+    ("token", r"\bsk-[A-Za-z0-9_-]{20,}"),
+    (
+        "token",
+        r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+    ),
+    (
+        "token",
+        r#"(?i)\b(?:proxy-)?authorization\s*:\s*(?:(?:bearer|basic|token|digest)\s+)?([^\s"'\\]+)"#,
+    ),
+    (
+        "password",
+        r#"(?i)\b(?:mysql|mysqldump|mysqladmin|mariadb)\b[^|;&\n]*?\s-p([^\s"'\\]+)"#,
+    ),
+    ("password", r#"\bsshpass\s+-p\s*([^\s"'\\]+)"#),
+    (
+        "password",
+        r#"\b[a-zA-Z][a-zA-Z0-9+.-]*://[^/\s:@"'\\]+:([^/\s@"'\\]+)@"#,
+    ),
+    (
+        "secret",
+        r#"(?i)--[\w-]*(?:secret|token|passw(?:or)?d|api-?key)[\w-]*\s+(?:\\?["'])?([^\s"'\\-][^\s"'\\]*)"#,
+    ),
+    (
+        "secret",
+        r#"(?i)[\w.-]*(?:secret|token|passw(?:or)?d|pwd|api[_-]?key|access[_-]?key|private[_-]?key|credentials?)[\w.-]*(?:\\?["'])?\s*[=:]\s*(?:\\?["'])?([^\s"'\\\[{-][^\s"'\\]*)"#,
+    ),
+];
+
+// random looking words at least this long are treated as secrets even without a known format
+const MIN_ENTROPY_LEN: usize = 20;
+// bits per char, hex tops out at 4 so commit hashes and the like are left alone
+const MIN_ENTROPY: f64 = 4.0;
+
+struct Rule {
+    kind: String,
+    pattern: Regex,
+}
+
+#[derive(Deserialize)]
+struct CustomRule {
+    name: String,
+    pattern: String,
+}
+
+#[derive(Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rules: Vec<CustomRule>,
+}
+
+struct Redaction {
+    kind: String,
+    placeholder: String,
+    secret: String,
+}
+
+// takes secrets out of everything that is sent to the model, the same secret always gets the same
+// placeholder so the model can still tell two commands used the same token
+pub struct Redactor {
+    rules: Vec<Rule>,
+    found: Vec<Redaction>,
+}
+
+impl Redactor {
+    // the built in rules plus the ones in $ZLI_HOME/redact.toml, for example
+    // [[rules]]
+    // name = "customer"
+    // pattern = "cust-[0-9]+"
+    pub fn load() -> Redactor {
+        let mut rules = BUILTIN_RULES
+            .iter()
+            .map(|(kind, pattern)| Rule {
+                kind: kind.to_string(),
+                pattern: Regex::new(pattern).unwrap_or_else(|e| panic!("{}", e)),
+            })
+            .collect::<Vec<Rule>>();
+        rules.extend(Redactor::load_custom_rules());
+
+        Redactor {
+            rules,
+            found: vec![],
+        }
+    }
+
+    fn load_custom_rules() -> Vec<Rule> {
+        let path = get_data_dir().join("redact.toml");
+        let buf = match fs::read_to_string(&path) {
+            Ok(buf) => buf,
+            Err(_) => return vec![],
+        };
+
+        let file = match toml::from_str::<RulesFile>(&buf) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("skipping bad redaction rules {}: {}", path.display(), e);
+                return vec![];
+            }
+        };
+
+        file.rules
+            .into_iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(pattern) => Some(Rule {
+                    kind: rule.name,
+                    pattern,
+                }),
+                Err(e) => {
+                    warn!("skipping redaction rule {}: {}", rule.name, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn get_placeholder(found: &mut Vec<Redaction>, kind: &str, secret: &str) -> String {
+        if let Some(redaction) = found.iter().find(|redaction| redaction.secret == secret) {
+            return redaction.placeholder.clone();
+        }
+
+        let count = found
+            .iter()
+            .filter(|redaction| redaction.kind == kind)
+            .count();
+        let placeholder = format!("<redacted_{}_{}>", kind, count + 1);
+        found.push(Redaction {
+            kind: kind.to_string(),
+            placeholder: placeholder.clone(),
+            secret: secret.to_string(),
+        });
+        placeholder
+    }
+
+    pub fn redact(&mut self, text: &str) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let mut redacted = String::new();
+            let mut last = 0;
+            for captures in rule.pattern.captures_iter(&text) {
+                let secret = match captures.get(1).or_else(|| captures.get(0)) {
+                    Some(secret) if !secret.is_empty() => secret,
+                    _ => continue,
+                };
+                redacted.push_str(&text[last..secret.start()]);
+                redacted.push_str(&Redactor::get_placeholder(
+                    &mut self.found,
+                    &rule.kind,
+                    secret.as_str(),
+                ));
+                last = secret.end();
+            }
+            redacted.push_str(&text[last..]);
+            text = redacted;
+        }
+
+        self.redact_high_entropy(&text)
+    }
+
+    fn redact_high_entropy(&mut self, text: &str) -> String {
+        let words = text
+            .split(|c: char| !(c.is_ascii_alphanumeric() || "+=_-".contains(c)))
+            .filter(|word| Redactor::is_high_entropy(word))
+            .collect::<Vec<&str>>();
+
+        let mut text = text.to_string();
+        for word in words {
+            let placeholder = Redactor::get_placeholder(&mut self.found, "secret", word);
+            text = text.replace(word, &placeholder);
+        }
+        text
+    }
+
+    fn is_high_entropy(word: &str) -> bool {
+        if word.len() < MIN_ENTROPY_LEN
+            || !word.chars().any(|c| c.is_ascii_digit())
+            || !word.chars().any(|c| c.is_ascii_alphabetic())
+        {
+            return false;
+        }
+
+        let mut counts = [0usize; 256];
+        for byte in word.bytes() {
+            counts[byte as usize] += 1;
+        }
+        let len = word.len() as f64;
+        let entropy: f64 = counts
+            .iter()
+            .filter(|count| **count > 0)
+            .map(|count| {
+                let p = *count as f64 / len;
+                -p * p.log2()
+            })
+            .sum();
+
+        entropy > MIN_ENTROPY
+    }
+
+    // the schemas are ours and are sent exactly as they are, their field names ("token",
+    // "password") look just like secrets to the rules, only the rest of the prompt is redacted
+    pub fn redact_prompt(&mut self, prompt: &str) -> String {
+        let schemas = Prompts::get_schemas()
+            .into_iter()
+            .filter(|schema| !schema.is_empty() && prompt.contains(schema.as_str()))
+            .enumerate()
+            .map(|(i, schema)| (format!("<schema_{}>", i), schema))
+            .collect::<Vec<(String, String)>>();
+
+        let mut prompt = prompt.to_string();
+        for (marker, schema) in &schemas {
+            prompt = prompt.replace(schema.as_str(), marker);
+        }
+        let mut prompt = self.redact(&prompt);
+        for (marker, schema) in &schemas {
+            prompt = prompt.replace(marker.as_str(), schema);
+        }
+        prompt
+    }
+
+    pub fn redact_request(&mut self, request: &OllamaRequest) -> OllamaRequest {
+        let mut request = request.clone();
+        for message in request.messages.iter_mut() {
+            message.content = self.redact_prompt(&message.content);
+        }
+        debug!("redacted {} secrets", self.found.len());
+        request
+    }
+
+    // puts the secrets back into what the model answered, the answer is json so they are escaped
+    // the same way
+    pub fn restore(&self, content: &str) -> String {
+        let mut content = content.to_string();
+        for redaction in &self.found {
+            let secret = to_string(&redaction.secret).unwrap_or_else(|e| panic!("{}", e));
+            content = content.replace(&redaction.placeholder, &secret[1..secret.len() - 1]);
+        }
+        content
+    }
+}
@@ -0,0 +1,69 @@
+pub struct Safety;
+
+impl Safety {
+    // very rough checks for commands that can do a lot of damage, the model gets things wrong often
+    // enough that these should never run on a single keypress
+    pub fn get_warnings(cmd: &str) -> Vec<String> {
+        let normalized = cmd.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let words = match shell_words::split(cmd) {
+            Ok(words) => words,
+            Err(_) => normalized.split(' ').map(|s| s.to_string()).collect(),
+        };
+        let has_word = |word: &str| words.iter().any(|w| w == word);
+
+        let mut warnings = vec![];
+
+        if has_word("rm") {
+            let recursive = words.iter().any(|w| {
+                w == "--recursive"
+                    || (w.starts_with('-') && !w.starts_with("--") && w.contains(['r', 'R']))
+            });
+            let broad_target = words
+                .iter()
+                .any(|w| ["/", "/*", "~", "~/", "*", ".", "..", "$HOME"].contains(&w.as_str()));
+            if recursive && broad_target {
+                warnings.push("recursively deletes a very broad path".to_string());
+            } else if recursive {
+                warnings.push("recursively deletes files".to_string());
+            }
+        }
+        if has_word("sudo") || has_word("doas") {
+            warnings.push("runs with elevated privileges".to_string());
+        }
+        if words.iter().any(|w| w.starts_with("mkfs")) || has_word("fdisk") || has_word("parted") {
+            warnings.push("formats or repartitions a disk".to_string());
+        }
+        if has_word("dd") && words.iter().any(|w| w.starts_with("of=/dev/")) {
+            warnings.push("writes directly to a device".to_string());
+        }
+        if normalized.contains("> /dev/sd") || normalized.contains(">/dev/sd") {
+            warnings.push("writes directly to a device".to_string());
+        }
+        if normalized.contains(":(){") || normalized.contains(":() {") {
+            warnings.push("looks like a fork bomb".to_string());
+        }
+        if (has_word("curl") || has_word("wget"))
+            && ["| sh", "| bash", "|sh", "|bash", "| zsh", "|zsh"]
+                .iter()
+                .any(|pipe| normalized.contains(pipe))
+        {
+            warnings.push("pipes remote content into a shell".to_string());
+        }
+        if has_word("chmod") && has_word("-R") && words.iter().any(|w| w == "777") {
+            warnings.push("makes everything below a path world writable".to_string());
+        }
+        if has_word("git") && has_word("push") && words.iter().any(|w| w == "--force" || w == "-f")
+        {
+            warnings.push("force pushes, which can drop commits on the remote".to_string());
+        }
+        if has_word("git") && has_word("reset") && has_word("--hard") {
+            warnings.push("throws away uncommitted changes".to_string());
+        }
+        if has_word("shutdown") || has_word("reboot") || has_word("halt") {
+            warnings.push("shuts down or reboots the machine".to_string());
+        }
+
+        warnings.dedup();
+        warnings
+    }
+}
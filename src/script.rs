@@ -0,0 +1,180 @@
+use std::fs;
+
+use log::debug;
+
+use crate::models::*;
+
+pub struct Script;
+
+impl Script {
+    // keys come from the model, so they are turned into something that works as a shell variable
+    pub fn get_variable_name(key: &str) -> String {
+        let name = key
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c.to_ascii_lowercase(),
+                false => '_',
+            })
+            .collect::<String>();
+
+        match name.starts_with(|c: char| c.is_ascii_digit()) || name.is_empty() {
+            true => format!("_{}", name),
+            false => name,
+        }
+    }
+
+    fn get_comment(text: &str) -> String {
+        text.lines()
+            .map(|line| format!("# {}", line.trim()).trim_end().to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // missing fields in the order they first show up, a key used by several commands is only
+    // asked for once
+    pub fn get_fields(suggestion: &ModelSuggestion) -> Vec<&MissingField> {
+        let mut fields: Vec<&MissingField> = vec![];
+        for command in &suggestion.commands {
+            for field in &command.missing_fields {
+                if !fields.iter().any(|f| f.key == field.key) {
+                    fields.push(field);
+                }
+            }
+        }
+        fields
+    }
+
+    // every missing field turns into a positional argument, the user is prompted for whatever
+    // isn't passed
+    pub fn get_script(suggestion: &ModelSuggestion) -> String {
+        let fields = Script::get_fields(suggestion);
+
+        let mut script = format!(
+            "#!/usr/bin/env bash\n{}\nset -euo pipefail\n",
+            Script::get_comment(&suggestion.reasoning)
+        );
+
+        let usage_args = fields
+            .iter()
+            .map(|field| format!(" [{}]", Script::get_variable_name(&field.key)))
+            .collect::<String>();
+        script.push_str("\nusage() {\n");
+        script.push_str(&format!(
+            "    echo \"usage: $(basename \"$0\"){}\"\n",
+            usage_args
+        ));
+        for field in &fields {
+            script.push_str(&format!(
+                "    echo {}\n",
+                shell_words::quote(&format!(
+                    "  {}  {}",
+                    Script::get_variable_name(&field.key),
+                    field.reasoning
+                ))
+            ));
+            for suggestion in &field.suggestions {
+                script.push_str(&format!(
+                    "    echo {}\n",
+                    shell_words::quote(&format!(
+                        "      e.g. {} - {}",
+                        suggestion.value, suggestion.reasoning
+                    ))
+                ));
+            }
+        }
+        script.push_str("}\n\n");
+        script.push_str(
+            "if [ \"${1:-}\" = \"-h\" ] || [ \"${1:-}\" = \"--help\" ]; then\n    usage\n    exit 0\nfi\n",
+        );
+
+        for (i, field) in fields.iter().enumerate() {
+            let name = Script::get_variable_name(&field.key);
+            script.push_str(&format!(
+                "\n{name}=\"${{{position}:-}}\"\nif [ -z \"${name}\" ]; then\n    read -r -p {prompt} {name}\nfi\n",
+                name = name,
+                position = i + 1,
+                prompt = shell_words::quote(&format!("{} ({}) -> ", name, field.reasoning)),
+            ));
+        }
+
+        for (i, command) in suggestion.commands.iter().enumerate() {
+            let cmd = Script::get_command(command);
+            script.push_str(&format!(
+                "\n# step {}: {}\n{}\n",
+                i + 1,
+                Script::get_comment(&command.reasoning).trim_start_matches("# "),
+                cmd
+            ));
+        }
+
+        script
+    }
+
+    // placeholders become quoted references to the variables holding the arguments, so a value
+    // with spaces or a * stays one word. quotes that only held the placeholder are dropped, one
+    // inside a longer single quoted string is closed and reopened around it
+    pub fn get_command(command: &SuggestedCommand) -> String {
+        let placeholders = command
+            .missing_fields
+            .iter()
+            .map(|field| {
+                (
+                    format!("<{}>", field.key),
+                    Script::get_variable_name(&field.key),
+                )
+            })
+            .collect::<Vec<(String, String)>>();
+
+        let mut cmd = String::new();
+        let mut quote: Option<char> = None;
+        let mut rest = command.cmd.as_str();
+        while let Some(c) = rest.chars().next() {
+            if let Some((placeholder, name)) = placeholders
+                .iter()
+                .find(|(placeholder, _)| rest.starts_with(placeholder.as_str()))
+            {
+                rest = &rest[placeholder.len()..];
+                match quote {
+                    Some('"') => cmd.push_str(&format!("${{{}}}", name)),
+                    Some(_) if cmd.ends_with('\'') && rest.starts_with('\'') => {
+                        cmd.pop();
+                        rest = &rest[1..];
+                        quote = None;
+                        cmd.push_str(&format!("\"${{{}}}\"", name));
+                    }
+                    Some(_) => cmd.push_str(&format!("'\"${{{}}}\"'", name)),
+                    None => cmd.push_str(&format!("\"${{{}}}\"", name)),
+                }
+                continue;
+            }
+
+            rest = &rest[c.len_utf8()..];
+            cmd.push(c);
+            match (quote, c) {
+                (None, '\'') | (None, '"') => quote = Some(c),
+                (Some(q), c) if q == c => quote = None,
+                // an escaped character can't start or end a quote
+                (None, '\\') | (Some('"'), '\\') => {
+                    if let Some(escaped) = rest.chars().next() {
+                        rest = &rest[escaped.len_utf8()..];
+                        cmd.push(escaped);
+                    }
+                }
+                _ => {}
+            }
+        }
+        cmd
+    }
+
+    pub fn write(path: &str, suggestion: &ModelSuggestion) {
+        debug!("writing script to {}", path);
+        fs::write(path, Script::get_script(suggestion)).unwrap_or_else(|e| panic!("{}", e));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+                .unwrap_or_else(|e| panic!("{}", e));
+        }
+    }
+}
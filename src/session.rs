@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Local;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::models::*;
+use crate::storage::{get_data_dir, get_key};
+
+// a chat transcript with the model, saved after every turn so it can be resumed later
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub created_at: String,
+    pub model: String,
+    pub messages: Vec<OllamaMessage>,
+}
+
+impl Session {
+    pub fn new(model: &str, messages: Vec<OllamaMessage>) -> Session {
+        let created_at = Local::now().format("%Y-%m-%d %H:%M:%S%.f").to_string();
+        let id = get_key(&[&created_at, model])[..8].to_string();
+
+        Session {
+            id,
+            created_at: created_at[..19].to_string(),
+            model: model.to_string(),
+            messages,
+        }
+    }
+
+    fn get_sessions_dir() -> PathBuf {
+        let dir = get_data_dir().join("sessions");
+        fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("{}", e));
+        dir
+    }
+
+    pub fn load(id: &str) -> Session {
+        let path = Session::get_sessions_dir().join(format!("{}.json", id));
+        debug!("loading session from {}", path.display());
+
+        let buf = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("no session {} at {}: {}", id, path.display(), e));
+        serde_json::from_str::<Session>(&buf).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn save(&self) {
+        let path = Session::get_sessions_dir().join(format!("{}.json", self.id));
+        debug!("saving session to {}", path.display());
+
+        let session_string = serde_json::to_string_pretty(self).unwrap_or_else(|e| panic!("{}", e));
+        fs::write(path, session_string).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    pub fn list() -> Vec<Session> {
+        let mut sessions = fs::read_dir(Session::get_sessions_dir())
+            .unwrap_or_else(|e| panic!("{}", e))
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let buf = fs::read_to_string(entry.path()).ok()?;
+                match serde_json::from_str::<Session>(&buf) {
+                    Ok(session) => Some(session),
+                    Err(e) => {
+                        warn!("skipping bad session {}: {}", entry.path().display(), e);
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<Session>>();
+
+        sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        sessions
+    }
+
+    // the first thing the user asked, used to tell sessions apart when listing them
+    pub fn get_first_query(&self) -> String {
+        self.messages
+            .iter()
+            .find(|message| message.role == "user")
+            .map(|message| message.content.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get_last_suggestions(&self) -> Vec<ModelSuggestion> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|message| message.role == "assistant")
+            .and_then(|message| {
+                serde_json::from_str::<OllamaPlaceholderResponse>(&message.content).ok()
+            })
+            .map(|response| response.response)
+            .unwrap_or_default()
+    }
+
+    pub fn push(&mut self, role: &str, content: String) {
+        self.messages.push(OllamaMessage {
+            role: role.to_string(),
+            content,
+        });
+    }
+}
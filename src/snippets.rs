@@ -0,0 +1,153 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::history_search::HistorySearch;
+use crate::models::*;
+use crate::storage::get_data_dir;
+
+// only the best few snippets are shown and put in the prompt
+const MAX_SNIPPETS: usize = 3;
+const MIN_SCORE: f64 = 0.5;
+
+// a team standard command the model can't know about, written by hand in a toml or yaml file
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Snippet {
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub cmd: String,
+    #[serde(default)]
+    pub missing_fields: Vec<MissingField>,
+}
+
+#[derive(Deserialize)]
+struct SnippetFile {
+    #[serde(default)]
+    snippets: Vec<Snippet>,
+}
+
+pub struct Snippets;
+
+impl Snippets {
+    // personal snippets live in the data dir, a shared team dir can be added with
+    // ZLI_TEAM_SNIPPETS
+    fn get_dirs() -> Vec<PathBuf> {
+        let mut dirs = vec![get_data_dir().join("snippets")];
+        if let Ok(team_dir) = env::var("ZLI_TEAM_SNIPPETS") {
+            dirs.push(PathBuf::from(team_dir));
+        }
+        dirs
+    }
+
+    fn load_file(path: &Path) -> Vec<Snippet> {
+        let buf = match fs::read_to_string(path) {
+            Ok(buf) => buf,
+            Err(e) => {
+                warn!("unable to read snippets from {}: {}", path.display(), e);
+                return vec![];
+            }
+        };
+
+        let file = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str::<SnippetFile>(&buf).map_err(|e| e.to_string()),
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str::<SnippetFile>(&buf).map_err(|e| e.to_string())
+            }
+            _ => return vec![],
+        };
+
+        match file {
+            Ok(file) => file.snippets,
+            Err(e) => {
+                warn!("skipping bad snippet file {}: {}", path.display(), e);
+                vec![]
+            }
+        }
+    }
+
+    pub fn load() -> Vec<Snippet> {
+        let mut paths = Snippets::get_dirs()
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+            .map(|entry| entry.path())
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+
+        let snippets = paths
+            .iter()
+            .flat_map(|path| Snippets::load_file(path))
+            .collect::<Vec<Snippet>>();
+        debug!("loaded {} snippets", snippets.len());
+        snippets
+    }
+
+    // scored like the history search, against the description, tags and command together
+    pub fn get_matching(query: &str, snippets: &[Snippet]) -> Vec<Snippet> {
+        let query_words = query
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<String>>();
+        if query_words.is_empty() {
+            return vec![];
+        }
+
+        let mut matches = snippets
+            .iter()
+            .map(|snippet| {
+                let text = format!(
+                    "{} {} {}",
+                    snippet.description,
+                    snippet.tags.join(" "),
+                    snippet.cmd
+                );
+                (HistorySearch::get_text_score(&query_words, &text), snippet)
+            })
+            .filter(|(score, _)| *score >= MIN_SCORE)
+            .collect::<Vec<(f64, &Snippet)>>();
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        matches
+            .into_iter()
+            .take(MAX_SNIPPETS)
+            .map(|(_, snippet)| snippet.clone())
+            .collect()
+    }
+
+    // placeholders without any metadata in the file still have to be asked for
+    fn get_missing_fields(snippet: &Snippet) -> Vec<MissingField> {
+        let mut fields = snippet.missing_fields.clone();
+        let mut rest = snippet.cmd.as_str();
+        while let Some(start) = rest.find('<') {
+            let end = match rest[start..].find('>') {
+                Some(end) => start + end,
+                None => break,
+            };
+            let key = &rest[start + 1..end];
+            if !key.is_empty() && !key.contains(' ') && !fields.iter().any(|f| f.key == key) {
+                fields.push(MissingField {
+                    key: key.to_string(),
+                    reasoning: format!("value for {}", key),
+                    suggestions: vec![],
+                });
+            }
+            rest = &rest[end + 1..];
+        }
+        fields
+    }
+
+    pub fn to_suggestion(snippet: &Snippet) -> ModelSuggestion {
+        ModelSuggestion {
+            reasoning: format!("snippet: {}", snippet.description),
+            commands: vec![SuggestedCommand {
+                cmd: snippet.cmd.clone(),
+                missing_fields: Snippets::get_missing_fields(snippet),
+                reasoning: snippet.description.clone(),
+            }],
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{to_value, Value};
+
+use crate::context::{ContextConfig, ContextProvider, ContextProviders, ProviderSettings};
+use crate::models::*;
+
+// missing fields with one of these in their key are about a remote machine
+const HOST_KEYS: [&str; 3] = ["host", "server", "machine"];
+// includes can include other files, this is as deep as we follow them
+const MAX_INCLUDE_DEPTH: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SshHost {
+    pub alias: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    // only the path, the key itself is never read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SshContext {
+    pub hosts: Vec<SshHost>,
+    pub known_hosts: Vec<String>,
+}
+
+// the hosts from ~/.ssh/config and the host names from ~/.ssh/known_hosts
+pub struct SshProvider {
+    settings: ProviderSettings,
+}
+
+impl SshProvider {
+    pub fn load(config: &ContextConfig) -> SshProvider {
+        SshProvider {
+            settings: config.get_settings("ssh", 1000, 300),
+        }
+    }
+
+    fn get_ssh_dir() -> Option<PathBuf> {
+        env::var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(home).join(".ssh"))
+    }
+
+    // Host lines start a new block, patterns with wildcards or negations aren't something you can
+    // connect to so they only give defaults and are left out
+    fn parse_config(path: &Path, depth: usize, hosts: &mut Vec<SshHost>) {
+        let buf = match fs::read_to_string(path) {
+            Ok(buf) => buf,
+            Err(_) => return,
+        };
+
+        let mut current: Vec<usize> = vec![];
+        for line in buf.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+                Some((key, value)) => (
+                    key.to_lowercase(),
+                    value
+                        .trim()
+                        .trim_start_matches('=')
+                        .trim()
+                        .trim_matches('"'),
+                ),
+                None => continue,
+            };
+
+            match key.as_str() {
+                "host" => {
+                    current = vec![];
+                    for alias in value.split_whitespace() {
+                        if alias.contains(['*', '?', '!']) {
+                            continue;
+                        }
+                        match hosts.iter().position(|host| host.alias == alias) {
+                            Some(i) => current.push(i),
+                            None => {
+                                current.push(hosts.len());
+                                hosts.push(SshHost {
+                                    alias: alias.to_string(),
+                                    ..SshHost::default()
+                                });
+                            }
+                        }
+                    }
+                }
+                "match" => current = vec![],
+                "include" if depth < MAX_INCLUDE_DEPTH => {
+                    for include in SshProvider::get_includes(value) {
+                        SshProvider::parse_config(&include, depth + 1, hosts);
+                    }
+                }
+                _ => {
+                    // the first value given for a host wins, same as with ssh itself
+                    for i in &current {
+                        let host = &mut hosts[*i];
+                        let field = match key.as_str() {
+                            "hostname" => &mut host.hostname,
+                            "user" => &mut host.user,
+                            "port" => &mut host.port,
+                            "identityfile" => &mut host.identity_file,
+                            _ => continue,
+                        };
+                        field.get_or_insert_with(|| value.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    // relative paths are relative to ~/.ssh, a * in the file name matches like it does in a shell
+    fn get_includes(value: &str) -> Vec<PathBuf> {
+        let ssh_dir = match SshProvider::get_ssh_dir() {
+            Some(dir) => dir,
+            None => return vec![],
+        };
+
+        let mut includes = vec![];
+        for pattern in value.split_whitespace() {
+            let pattern = match pattern.strip_prefix("~/") {
+                Some(rest) => ssh_dir.parent().unwrap_or(&ssh_dir).join(rest),
+                None => ssh_dir.join(pattern),
+            };
+            let name = pattern
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if !name.contains('*') {
+                includes.push(pattern);
+                continue;
+            }
+
+            let (prefix, suffix) = name.split_once('*').unwrap_or((&name, ""));
+            let mut matches = pattern
+                .parent()
+                .and_then(|dir| fs::read_dir(dir).ok())
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path())
+                        .filter(|path| {
+                            let name = path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            name.starts_with(prefix) && name.ends_with(suffix)
+                        })
+                        .collect::<Vec<PathBuf>>()
+                })
+                .unwrap_or_default();
+            matches.sort();
+            includes.extend(matches);
+        }
+        includes
+    }
+
+    // only the host names, every line is `hosts keytype key` and the key is dropped. hashed
+    // entries don't give the name away, so they are skipped
+    fn parse_known_hosts(path: &Path) -> Vec<String> {
+        let buf = fs::read_to_string(path).unwrap_or_default();
+
+        let mut names: Vec<String> = vec![];
+        for line in buf.lines() {
+            let mut fields = line.split_whitespace();
+            let hosts = match fields.next() {
+                Some(field) if field.starts_with('#') => continue,
+                Some(field) if field.starts_with('@') => fields.next(),
+                field => field,
+            };
+
+            for host in hosts.unwrap_or_default().split(',') {
+                if host.is_empty() || host.starts_with("|1|") || host.contains(['*', '?', '!']) {
+                    continue;
+                }
+                let host = match host
+                    .strip_prefix('[')
+                    .and_then(|host| host.split_once("]:"))
+                {
+                    Some((host, _)) => host,
+                    None => host,
+                };
+                if !names.iter().any(|name| name == host) {
+                    names.push(host.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    fn is_host_field(key: &str) -> bool {
+        key == "remote" || HOST_KEYS.iter().any(|host_key| key.contains(host_key))
+    }
+
+    pub fn add_host_suggestions(suggestions: &mut [ModelSuggestion], ctx: &Context) {
+        let ssh = match ctx.get_extra::<SshContext>("ssh") {
+            Some(ssh) => ssh,
+            None => return,
+        };
+
+        let hosts = ssh
+            .hosts
+            .iter()
+            .map(|host| MissingFieldSuggestion {
+                value: host.alias.clone(),
+                reasoning: SshProvider::get_description(host),
+            })
+            .collect::<Vec<MissingFieldSuggestion>>();
+        ContextProviders::add_field_suggestions(suggestions, SshProvider::is_host_field, &hosts);
+    }
+
+    // "Host db in ~/.ssh/config, admin@10.0.0.5:2222"
+    fn get_description(host: &SshHost) -> String {
+        let mut target = host.hostname.clone().unwrap_or_else(|| host.alias.clone());
+        if let Some(user) = &host.user {
+            target = format!("{}@{}", user, target);
+        }
+        if let Some(port) = &host.port {
+            target = format!("{}:{}", target, port);
+        }
+        format!("Host {} in ~/.ssh/config, {}", host.alias, target)
+    }
+}
+
+impl ContextProvider for SshProvider {
+    fn name(&self) -> &str {
+        "ssh"
+    }
+
+    fn settings(&self) -> &ProviderSettings {
+        &self.settings
+    }
+
+    fn collect(&self, _cwd: &Path) -> Option<Value> {
+        let ssh_dir = SshProvider::get_ssh_dir()?;
+
+        let mut hosts = vec![];
+        SshProvider::parse_config(&ssh_dir.join("config"), 0, &mut hosts);
+        let known_hosts = SshProvider::parse_known_hosts(&ssh_dir.join("known_hosts"));
+        debug!(
+            "found {} ssh hosts and {} known hosts",
+            hosts.len(),
+            known_hosts.len()
+        );
+        if hosts.is_empty() && known_hosts.is_empty() {
+            return None;
+        }
+
+        to_value(SshContext { hosts, known_hosts }).ok()
+    }
+}
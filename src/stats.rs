@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::Local;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::models::*;
+use crate::storage::get_data_dir;
+
+const NANOS_PER_SEC: f64 = 1_000_000_000.0;
+
+// one line in the stats file, durations are in nanoseconds just like ollama reports them
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Usage {
+    pub datetime: String,
+    pub model: String,
+    pub prompt_eval_count: u64,
+    pub prompt_eval_duration: u64,
+    pub eval_count: u64,
+    pub eval_duration: u64,
+    pub load_duration: u64,
+    pub total_duration: u64,
+    // the prompt templates the response was for, see Prompts::get_version
+    #[serde(default)]
+    pub prompt_version: String,
+}
+
+impl Usage {
+    pub fn from_response(response: &OllamaResponse, prompt_version: &str) -> Usage {
+        Usage {
+            datetime: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            model: response.model.clone(),
+            prompt_eval_count: response.prompt_eval_count,
+            prompt_eval_duration: response.prompt_eval_duration,
+            eval_count: response.eval_count,
+            eval_duration: response.eval_duration,
+            load_duration: response.load_duration,
+            total_duration: response.total_duration,
+            prompt_version: prompt_version.to_string(),
+        }
+    }
+
+    pub fn tokens_per_sec(&self) -> f64 {
+        match self.eval_duration {
+            0 => 0.0,
+            duration => self.eval_count as f64 / (duration as f64 / NANOS_PER_SEC),
+        }
+    }
+}
+
+pub struct Stats;
+
+impl Stats {
+    fn get_stats_file_path() -> PathBuf {
+        get_data_dir().join("stats.jsonl")
+    }
+
+    // the stats file is append only, one json object per line
+    pub fn save(usage: &Usage) {
+        let path = Stats::get_stats_file_path();
+        debug!("saving usage to {}", path.display());
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|e| panic!("{}", e));
+        let line = serde_json::to_string(usage).unwrap_or_else(|e| panic!("{}", e));
+        writeln!(file, "{}", line).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    pub fn load() -> Vec<Usage> {
+        let buf = fs::read_to_string(Stats::get_stats_file_path()).unwrap_or_default();
+
+        buf.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<Usage>(line) {
+                Ok(usage) => Some(usage),
+                Err(e) => {
+                    warn!("skipping bad line in stats file: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn print_usage(usage: &Usage) {
+        println!(
+            "{} | prompt {} tokens | {} tokens at {:.2} tokens/sec | load {:.2}s | total {:.2}s",
+            usage.model,
+            usage.prompt_eval_count,
+            usage.eval_count,
+            usage.tokens_per_sec(),
+            usage.load_duration as f64 / NANOS_PER_SEC,
+            usage.total_duration as f64 / NANOS_PER_SEC,
+        );
+    }
+
+    // groups the usage per model and per day
+    pub fn print_summary(usages: &[Usage]) {
+        if usages.is_empty() {
+            println!("no usage recorded yet");
+            return;
+        }
+
+        let mut groups: BTreeMap<(String, String), Vec<&Usage>> = BTreeMap::new();
+        for usage in usages {
+            let day = usage.datetime.chars().take(10).collect::<String>();
+            groups
+                .entry((usage.model.clone(), day))
+                .or_default()
+                .push(usage);
+        }
+
+        println!(
+            "{:<16} {:<10} {:>7} {:>13} {:>11} {:>10} {:>9} {:>10}",
+            "model",
+            "day",
+            "queries",
+            "prompt tokens",
+            "eval tokens",
+            "tokens/sec",
+            "avg load",
+            "avg total"
+        );
+        for ((model, day), group) in groups {
+            let queries = group.len() as u64;
+            let prompt_tokens: u64 = group.iter().map(|u| u.prompt_eval_count).sum();
+            let eval_tokens: u64 = group.iter().map(|u| u.eval_count).sum();
+            let eval_duration: u64 = group.iter().map(|u| u.eval_duration).sum();
+            let load_duration: u64 = group.iter().map(|u| u.load_duration).sum();
+            let total_duration: u64 = group.iter().map(|u| u.total_duration).sum();
+
+            let tokens_per_sec = match eval_duration {
+                0 => 0.0,
+                duration => eval_tokens as f64 / (duration as f64 / NANOS_PER_SEC),
+            };
+
+            println!(
+                "{:<16} {:<10} {:>7} {:>13} {:>11} {:>10.2} {:>8.2}s {:>9.2}s",
+                model,
+                day,
+                queries,
+                prompt_tokens,
+                eval_tokens,
+                tokens_per_sec,
+                load_duration as f64 / NANOS_PER_SEC / queries as f64,
+                total_duration as f64 / NANOS_PER_SEC / queries as f64,
+            );
+        }
+    }
+}
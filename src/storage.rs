@@ -0,0 +1,28 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// everything the cli keeps between runs lives in $ZLI_HOME, or ~/.zli when that isn't set
+pub fn get_data_dir() -> PathBuf {
+    let dir = match env::var("ZLI_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").unwrap_or_else(|e| panic!("{}", e))).join(".zli"),
+    };
+
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("{}", e));
+    dir
+}
+
+// stable hash used to name the files we keep on disk, fnv-1a since DefaultHasher is not
+// guaranteed to give the same result across rust releases
+pub fn get_key(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    format!("{:016x}", hash)
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+
+use crate::environment::Environment;
+use crate::explain::Explain;
+use crate::models::*;
+
+const BUILTINS: [&str; 38] = [
+    ".", ":", "[", "alias", "bg", "builtin", "cd", "command", "declare", "dirs", "echo", "eval",
+    "exec", "exit", "export", "false", "fg", "hash", "history", "jobs", "kill", "let", "local",
+    "popd", "printf", "pushd", "pwd", "read", "set", "shift", "source", "test", "trap", "true",
+    "type", "umask", "unset", "wait",
+];
+
+// wrappers that run the next word as the actual program
+const WRAPPERS: [&str; 6] = ["sudo", "doas", "env", "time", "nohup", "exec"];
+
+// programs whose --help only prints their usage. anything else the model names could do
+// whatever it likes with an argument it doesn't know, so its flags are only checked against its
+// man page
+const HELP_PROGRAMS: [&str; 44] = [
+    "awk", "cargo", "cat", "chmod", "chown", "cp", "curl", "cut", "date", "df", "diff", "docker",
+    "du", "find", "git", "grep", "gzip", "head", "jq", "kubectl", "ln", "ls", "make", "mkdir",
+    "mv", "npm", "ps", "rg", "rm", "rmdir", "rsync", "sed", "sort", "stat", "tail", "tar", "tee",
+    "touch", "tr", "uniq", "unzip", "wc", "wget", "xargs",
+];
+
+// help output is read before the user confirmed anything, so the only things ever run are man
+// and `--help` of the programs above, with a scratch dir, no stdin and not much time. that keeps
+// stray output and pagers out of the way
+const HELP_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct InvalidFlag {
+    pub cmd: String,
+    pub program: String,
+    pub flag: String,
+}
+
+pub struct Verify;
+
+impl Verify {
+    // every stage of a command starting at the program it runs, env assignments and sudo and the
+    // like are skipped
+    fn get_invocations(cmd: &str) -> Vec<Vec<String>> {
+        Explain::split_stages(cmd)
+            .iter()
+            .filter_map(|stage| {
+                let words = shell_words::split(stage).ok()?;
+                let start = words.iter().position(|word| {
+                    !((word.contains('=') && !word.starts_with('='))
+                        || WRAPPERS.contains(&word.as_str())
+                        || word.starts_with('-'))
+                })?;
+                match words[start].starts_with('<') {
+                    true => None,
+                    false => Some(words[start..].to_vec()),
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_binaries(cmd: &str) -> Vec<String> {
+        Verify::get_invocations(cmd)
+            .into_iter()
+            .map(|words| words[0].clone())
+            .collect()
+    }
+
+    pub fn is_builtin(name: &str) -> bool {
+        BUILTINS.contains(&name)
+    }
+
+    pub fn binary_exists(name: &str) -> bool {
+        if Verify::is_builtin(name) {
+            return true;
+        }
+        if name.contains('/') {
+            return Path::new(name).is_file();
+        }
+        Verify::find_in_path(name).is_some()
+    }
+
+    // bare names only, a path like ./deploy.sh is never looked up
+    fn find_in_path(name: &str) -> Option<PathBuf> {
+        if name.is_empty() || name.contains('/') {
+            return None;
+        }
+        env::var_os("PATH").and_then(|path| {
+            env::split_paths(&path)
+                .map(|dir| dir.join(name))
+                .find(|path| path.is_file())
+        })
+    }
+
+    pub fn get_missing_binaries(cmd: &str) -> Vec<String> {
+        Verify::get_binaries(cmd)
+            .into_iter()
+            .filter(|binary| !Verify::binary_exists(binary))
+            .collect()
+    }
+
+    pub fn get_install_hint(binary: &str, environment: &Environment) -> Option<String> {
+        let manager = environment.package_manager.as_ref()?;
+        let install = Environment::get_install_command(manager)?;
+        Some(format!("{} {}", install, binary))
+    }
+
+    // reads the pipes on their own threads so a long man page can't fill them up and block the
+    // child until the timeout
+    fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+        thread::spawn(move || {
+            let mut buf = String::new();
+            if let Some(mut pipe) = pipe {
+                _ = pipe.read_to_string(&mut buf);
+            }
+            buf
+        })
+    }
+
+    fn wait_with_timeout(child: &mut Child) -> bool {
+        let start = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(_)) => return true,
+                Ok(None) if start.elapsed() < HELP_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(20))
+                }
+                _ => {
+                    _ = child.kill();
+                    _ = child.wait();
+                    return false;
+                }
+            }
+        }
+    }
+
+    fn run_for_help(program: &Path, args: &[&str]) -> Option<String> {
+        let scratch = env::temp_dir().join("zli-verify");
+        fs::create_dir_all(&scratch).ok()?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(&scratch)
+            .env_clear()
+            .env("PATH", env::var_os("PATH").unwrap_or_default())
+            .env("HOME", &scratch)
+            .env("LANG", "C")
+            .env("TERM", "dumb")
+            .env("PAGER", "cat")
+            .env("MANPAGER", "cat")
+            .env("GIT_PAGER", "cat")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let stdout = Verify::read_pipe(child.stdout.take());
+        let stderr = Verify::read_pipe(child.stderr.take());
+
+        if !Verify::wait_with_timeout(&mut child) {
+            warn!("{} {} timed out", program.display(), args.join(" "));
+            return None;
+        }
+        let output = format!(
+            "{}\n{}",
+            stdout.join().unwrap_or_default(),
+            stderr.join().unwrap_or_default()
+        );
+        Some(output)
+    }
+
+    // the man page, then --help of the program itself when it is one of HELP_PROGRAMS. a
+    // subcommand only ever gets its own man page, like git-commit, the word the model picked is
+    // never passed to the program
+    fn get_help(program: &str, subcommand: Option<&str>) -> Option<String> {
+        let man = Verify::find_in_path("man");
+        let mut attempts: Vec<(PathBuf, Vec<String>)> = vec![];
+        match subcommand {
+            Some(subcommand) => {
+                if let Some(man) = &man {
+                    attempts.push((man.clone(), vec![format!("{}-{}", program, subcommand)]));
+                }
+            }
+            None => {
+                if let Some(man) = &man {
+                    attempts.push((man.clone(), vec![program.to_string()]));
+                }
+                if HELP_PROGRAMS.contains(&program) {
+                    attempts.push((Verify::find_in_path(program)?, vec!["--help".to_string()]));
+                }
+            }
+        }
+
+        attempts.iter().find_map(|(path, args)| {
+            let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>();
+            let help = Verify::run_for_help(path, &args)?;
+            let flag_count = help
+                .split_whitespace()
+                .filter(|word| word.starts_with('-') && word.len() > 1)
+                .count();
+            match flag_count >= 3 {
+                true => Some(help),
+                false => None,
+            }
+        })
+    }
+
+    // the flag has to show up as a word of its own, so -l doesn't match --long or -la
+    fn help_mentions(help: &str, flag: &str) -> bool {
+        help.match_indices(flag).any(|(i, _)| {
+            let before = help[..i].chars().next_back();
+            let after = help[i + flag.len()..].chars().next();
+            !before.is_some_and(|c| c.is_alphanumeric() || c == '-')
+                && !after.is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        })
+    }
+
+    fn is_valid_flag(help: &str, flag: &str) -> bool {
+        if Verify::help_mentions(help, flag) {
+            return true;
+        }
+
+        // -la is -l and -a
+        let short = flag.trim_start_matches('-');
+        !flag.starts_with("--")
+            && short.chars().count() > 1
+            && short
+                .chars()
+                .all(|c| Verify::help_mentions(help, &format!("-{}", c)))
+    }
+
+    pub fn get_invalid_flags(suggestions: &[ModelSuggestion]) -> Vec<InvalidFlag> {
+        let mut helps: HashMap<String, Option<String>> = HashMap::new();
+        let mut invalid = vec![];
+
+        for command in suggestions
+            .iter()
+            .flat_map(|suggestion| &suggestion.commands)
+        {
+            for words in Verify::get_invocations(&command.cmd) {
+                let program = &words[0];
+                if Verify::is_builtin(program) || Verify::find_in_path(program).is_none() {
+                    continue;
+                }
+
+                let subcommand = words
+                    .get(1)
+                    .filter(|word| word.chars().all(|c| c.is_ascii_lowercase() || c == '-'))
+                    .filter(|word| !word.starts_with('-'))
+                    .map(|word| word.as_str());
+                let help_key = format!("{} {}", program, subcommand.unwrap_or_default());
+                let help = helps
+                    .entry(help_key)
+                    .or_insert_with(|| Verify::get_help(program, subcommand));
+                let help = match help {
+                    Some(help) => help,
+                    None => continue,
+                };
+
+                // a lone - is stdin and -- ends the flags
+                for word in words.iter().skip(1) {
+                    if word == "--" {
+                        break;
+                    }
+                    if !word.starts_with('-') || word == "-" || word.contains('<') {
+                        continue;
+                    }
+                    let flag = word.split('=').next().unwrap_or_default();
+                    if !Verify::is_valid_flag(help, flag) {
+                        debug!("{} is not a flag of {}", flag, program);
+                        invalid.push(InvalidFlag {
+                            cmd: command.cmd.clone(),
+                            program: program.clone(),
+                            flag: flag.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        invalid
+    }
+
+    pub fn get_reprompt_message(invalid: &[InvalidFlag]) -> String {
+        let report = invalid
+            .iter()
+            .map(|flag| {
+                format!(
+                    "`{}`: {} is not a flag of {} on this machine",
+                    flag.cmd, flag.flag, flag.program
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        format!(
+            "Some of the suggested commands use flags that don't exist here, fix them and send all of the suggestions again.\n{}",
+            report
+        )
+    }
+}
@@ -0,0 +1,104 @@
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+fn get_messages(request: &str) -> Vec<Value> {
+    let request: Value = serde_json::from_str(request).unwrap();
+    request["messages"].as_array().unwrap().clone()
+}
+
+#[test]
+fn agent_sends_command_output_back_until_done() {
+    let root = setup("agent_sends_command_output_back_until_done");
+    let (url, requests) = start_server_with(&["agent_step.json", "agent_done.json"]);
+
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, "--agent", QUERY],
+        "0\ny\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("step 1 - check what is in the directory first"));
+    assert!(stdout.contains("run `echo hello && ls`?"), "{}", stdout);
+    assert!(stdout.contains("done - main.rs is the only file"));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let messages = get_messages(&requests[1]);
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[2]["role"], "assistant");
+    let result = messages[3]["content"].as_str().unwrap();
+    assert!(
+        result.contains("command -> echo hello && ls\n"),
+        "{}",
+        result
+    );
+    assert!(result.contains("exit status -> 0\n"));
+    assert!(result.contains("stdout ->\nhello\nmain.rs\n"));
+}
+
+#[test]
+fn agent_stops_at_the_step_limit() {
+    let root = setup("agent_stops_at_the_step_limit");
+    let (url, requests) = start_server("agent_step.json");
+
+    let output = run_with_stdin(
+        &root,
+        &[
+            "--backend",
+            "ollama",
+            "--url",
+            &url,
+            "--agent",
+            "--max-steps",
+            "2",
+            QUERY,
+        ],
+        "0\ny\n0\ny\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("stopped after 2 steps"), "{}", stdout);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn agent_needs_a_full_yes_for_risky_steps() {
+    let root = setup("agent_needs_a_full_yes_for_risky_steps");
+    let (url, requests) = start_server("agent_risky.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, "--agent", QUERY],
+        "y\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("warning: `rm -rf *` recursively deletes a very broad path"));
+    assert!(stdout.contains("stopping the agent"));
+    assert!(root.join("work").join("main.rs").exists());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn agent_reports_a_malformed_response() {
+    let root = setup("agent_reports_a_malformed_response");
+    let (url, _) = start_server("malformed.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, "--agent", QUERY],
+        "",
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(
+        stderr.contains("Unable to parse the model response: EOF while parsing"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
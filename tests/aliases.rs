@@ -0,0 +1,138 @@
+use std::fs;
+use std::process::Command;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn saved_alias_becomes_a_shell_function() {
+    let root = setup("saved_alias_becomes_a_shell_function");
+    let (url, _) = start_server("fix.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, "fix"],
+        "n\nsave greet-file\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("saved greet-file"), "{}", stdout);
+    assert!(!stdout.contains("executing cmd"));
+
+    let list = run_command(&root, &["aliases", "list"]);
+    let list = String::from_utf8_lossy(&list.stdout);
+    assert!(list.contains("greet-file - psuh is a typo of push\n    echo <greeting> > fixed.txt\n"));
+
+    let hook = run_command(&root, &["hook", "bash"]);
+    let hook = String::from_utf8_lossy(&hook.stdout);
+    assert!(hook.contains("PROMPT_COMMAND=\"_zli_precmd"));
+    assert!(hook.contains("greet-file() {\n"), "{}", hook);
+
+    // the function stops at the failing step, like picking the suggestion does
+    let export = run_command(&root, &["aliases", "export"]);
+    let functions = String::from_utf8_lossy(&export.stdout).to_string();
+    assert!(functions.contains("    local greeting=\"${1:?usage: greet-file greeting}\"\n"));
+    let run = Command::new("bash")
+        .arg("-c")
+        .arg(format!("{}\ngreet-file hi", functions))
+        .current_dir(root.join("work"))
+        .output()
+        .unwrap();
+    assert!(!run.status.success());
+    assert_eq!(
+        fs::read_to_string(root.join("work").join("fixed.txt")).unwrap(),
+        "hi\n"
+    );
+    assert!(!root.join("work").join("never.txt").exists());
+
+    let json = run_command(&root, &["aliases", "export", "--format", "json"]);
+    assert!(String::from_utf8_lossy(&json.stdout).contains("\"name\": \"greet-file\""));
+
+    let remove = run_command(&root, &["aliases", "remove", "greet-file"]);
+    assert!(String::from_utf8_lossy(&remove.stdout).contains("removed greet-file"));
+    let list = run_command(&root, &["aliases", "list"]);
+    assert!(String::from_utf8_lossy(&list.stdout).contains("no aliases saved yet"));
+}
+
+#[test]
+fn alias_names_must_work_as_function_names() {
+    let root = setup("alias_names_must_work_as_function_names");
+    let (url, _) = start_server("fix.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, "fix"],
+        "n\nsave rm -rf\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("rm -rf can't be used as a name"),
+        "{}",
+        stdout
+    );
+    assert!(!root.join("home").join("aliases.json").exists());
+}
+
+#[test]
+fn alias_names_must_not_shadow_commands() {
+    let root = setup("alias_names_must_not_shadow_commands");
+    let (url, _) = start_server("fix.json");
+
+    for name in ["ls", "cd", "while"] {
+        let output = run_with_stdin(
+            &root,
+            &["--backend", "ollama", "--url", &url, "fix"],
+            &format!("n\nsave {}\n", name),
+        );
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.contains(&format!(
+                "{} is already a command or shell builtin, pick another name",
+                name
+            )),
+            "{}",
+            stdout
+        );
+    }
+    assert!(!root.join("home").join("aliases.json").exists());
+}
+
+#[test]
+fn alias_arguments_stay_one_word() {
+    let root = setup("alias_arguments_stay_one_word");
+    let (url, _) = start_server("quoted.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, "search"],
+        "save find-pattern\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("saved find-pattern"), "{}", stdout);
+
+    let export = run_command(&root, &["aliases", "export"]);
+    let functions = String::from_utf8_lossy(&export.stdout).to_string();
+    assert!(
+        functions.contains("    printf '%s|%s\\n' \"${pattern}\" \"in ${pattern}\" > found.txt\n"),
+        "{}",
+        functions
+    );
+
+    fs::write(root.join("work").join("b.txt"), "").unwrap();
+    let run = Command::new("bash")
+        .arg("-c")
+        .arg(format!("{}\nfind-pattern 'a *b*'", functions))
+        .current_dir(root.join("work"))
+        .output()
+        .unwrap();
+    assert!(
+        run.status.success(),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("work").join("found.txt")).unwrap(),
+        "a *b*|in a *b*\n"
+    );
+}
@@ -0,0 +1,129 @@
+use std::fs;
+
+use serde_json::json;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn repeated_query_is_served_from_cache() {
+    let root = setup("repeated_query_is_served_from_cache");
+    let (url, requests) = start_server("well_formed.json");
+
+    let first = run(&root, &["--backend", "ollama", "--url", &url]);
+    assert!(first.status.success());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    let second = run(&root, &["--backend", "ollama", "--url", &url]);
+    let stderr = String::from_utf8_lossy(&second.stderr);
+    assert!(second.status.success());
+    assert!(stderr.contains("cache hit"), "{}", stderr);
+    assert!(stderr.contains("git commit -m <message>"), "{}", stderr);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn only_a_change_outside_the_history_misses_the_cache() {
+    let root = setup("only_a_change_outside_the_history_misses_the_cache");
+    let (url, requests) = start_server("well_formed.json");
+    fs::write(
+        root.join("work").join("Makefile"),
+        "build:\n\tcargo build\n",
+    )
+    .unwrap();
+
+    run(&root, &["--backend", "ollama", "--url", &url]);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    // a command run since then doesn't change the answer
+    fs::write(
+        root.join("history.json"),
+        r#"[{"dir": "/home/user/project", "cmd": "cargo build", "datetime": "2024-11-04 06:13:34"},
+            {"dir": "/home/user/project", "cmd": "ls", "datetime": "2024-11-04 06:14:00"}]"#,
+    )
+    .unwrap();
+    let second = run(&root, &["--backend", "ollama", "--url", &url]);
+    assert!(String::from_utf8_lossy(&second.stderr).contains("cache hit"));
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    // a manifest that is excerpted into the context does
+    fs::write(
+        root.join("work").join("Makefile"),
+        "build:\n\tcargo build --release\n",
+    )
+    .unwrap();
+    let third = run(&root, &["--backend", "ollama", "--url", &url]);
+    assert!(!String::from_utf8_lossy(&third.stderr).contains("cache hit"));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn no_cache_always_asks_the_model() {
+    let root = setup("no_cache_always_asks_the_model");
+    let (url, requests) = start_server("well_formed.json");
+
+    run(&root, &["--backend", "ollama", "--url", &url]);
+    run(&root, &["--no-cache", "--backend", "ollama", "--url", &url]);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn empty_responses_are_not_cached() {
+    let root = setup("empty_responses_are_not_cached");
+    let (url, requests) = start_server("empty.json");
+
+    run(&root, &["--backend", "ollama", "--url", &url]);
+    run(&root, &["--backend", "ollama", "--url", &url]);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn cache_clear_removes_entries() {
+    let root = setup("cache_clear_removes_entries");
+    let (url, requests) = start_server("well_formed.json");
+
+    run(&root, &["--backend", "ollama", "--url", &url]);
+    let output = run_command(&root, &["cache", "clear"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("removed 1 cached responses"));
+
+    run(&root, &["--backend", "ollama", "--url", &url]);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn new_feedback_is_not_answered_from_the_cache() {
+    let root = setup("new_feedback_is_not_answered_from_the_cache");
+    let (url, requests) = start_server("well_formed.json");
+
+    run(&root, &["--backend", "ollama", "--url", &url]);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    // a pick for the same query goes into the prompt as an example, so the old answer is stale
+    let choice = json!({
+        "datetime": "2024-11-04 06:13:34",
+        "query": QUERY,
+        "cwd": root.join("work"),
+        "picked": {
+            "reasoning": "runs the tests",
+            "commands": [{"reasoning": "the tests", "cmd": "cargo test", "missing_fields": []}]
+        },
+        "skipped": [],
+        "values": []
+    });
+    fs::create_dir_all(root.join("home")).unwrap();
+    fs::write(
+        root.join("home").join("feedback.jsonl"),
+        format!("{}\n", choice),
+    )
+    .unwrap();
+
+    let second = run(&root, &["--backend", "ollama", "--url", &url]);
+    assert!(!String::from_utf8_lossy(&second.stderr).contains("cache hit"));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+
+    // the same feedback again is served from the cache
+    let third = run(&root, &["--backend", "ollama", "--url", &url]);
+    assert!(String::from_utf8_lossy(&third.stderr).contains("cache hit"));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
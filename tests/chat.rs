@@ -0,0 +1,97 @@
+use std::fs;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+fn get_session_id(stdout: &str) -> String {
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("session "))
+        .unwrap()
+        .to_string()
+}
+
+fn load_session(root: &std::path::Path, id: &str) -> Value {
+    let path = root
+        .join("home")
+        .join("sessions")
+        .join(format!("{}.json", id));
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn chat_sends_follow_up_with_transcript() {
+    let root = setup("chat_sends_follow_up_with_transcript");
+    let (url, requests) = start_server("well_formed.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--chat", "--backend", "ollama", "--url", &url, QUERY],
+        "no, only the unit tests\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("    git commit -m <message>"), "{}", stdout);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let follow_up: Value = serde_json::from_str(&requests[1]).unwrap();
+    let roles = follow_up["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["role"].as_str().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(roles, ["system", "user", "assistant", "user"]);
+    assert_eq!(
+        follow_up["messages"][3]["content"],
+        "no, only the unit tests"
+    );
+
+    let session = load_session(&root, &get_session_id(&stdout));
+    assert_eq!(session["messages"].as_array().unwrap().len(), 5);
+}
+
+#[test]
+fn resume_continues_saved_session() {
+    let root = setup("resume_continues_saved_session");
+    let (url, requests) = start_server("well_formed.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--chat", "--backend", "ollama", "--url", &url, QUERY],
+        "",
+    );
+    let id = get_session_id(&String::from_utf8_lossy(&output.stdout));
+
+    let output = run_with_stdin(
+        &root,
+        &[
+            "--resume",
+            &id,
+            "--backend",
+            "ollama",
+            "--url",
+            &url,
+            "use",
+            "nextest",
+        ],
+        "and skip the slow ones\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains(&format!("resuming session {}", id)));
+    assert_eq!(requests.lock().unwrap().len(), 3);
+
+    let session = load_session(&root, &id);
+    let messages = session["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 7);
+    assert_eq!(messages[3]["content"], "use nextest");
+    assert_eq!(messages[5]["content"], "and skip the slow ones");
+
+    let output = run_command(&root, &["sessions"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("{} ", id)));
+}
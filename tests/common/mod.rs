@@ -0,0 +1,167 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::Value;
+
+pub const QUERY: &str = "run the tests";
+
+// every test gets its own directory with a fixed history file and a fixed working directory,
+// so the prompt and the cassette key stay the same between the record and the replay run
+pub fn setup(name: &str) -> PathBuf {
+    setup_with_history(
+        name,
+        r#"[{"dir": "/home/user/project", "cmd": "cargo build", "datetime": "2024-11-04 06:13:34"}]"#,
+    )
+}
+
+pub fn setup_with_history(name: &str, history: &str) -> PathBuf {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("work")).unwrap();
+    fs::write(root.join("work").join("main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("history.json"), history).unwrap();
+
+    root
+}
+
+pub fn run(root: &Path, args: &[&str]) -> Output {
+    let mut args = args.to_vec();
+    args.push(QUERY);
+    run_command(root, &args)
+}
+
+// same as run, but without tacking the query on at the end, for subcommands
+pub fn run_command(root: &Path, args: &[&str]) -> Output {
+    run_with_stdin(root, args, "")
+}
+
+pub fn run_with_stdin(root: &Path, args: &[&str], input: &str) -> Output {
+    run_with_env(root, args, input, &[])
+}
+
+pub fn run_with_env(root: &Path, args: &[&str], input: &str, envs: &[(&str, &str)]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-cli"))
+        .current_dir(root.join("work"))
+        .env("ZLI_HOME", root.join("home"))
+        .envs(envs.iter().copied())
+        .arg("-l")
+        .arg("debug")
+        .arg("--history")
+        .arg(root.join("history.json"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // the process may be gone before it reads anything, that shows up in the output instead
+    _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+pub fn cassette_dir(root: &Path) -> String {
+    root.join("cassettes").to_str().unwrap().to_string()
+}
+
+pub fn fixture_path(fixture: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("cassettes")
+        .join(fixture)
+}
+
+// the context section of what `prompt` prints, as the single line of json it is sent as
+pub fn get_context(stdout: &str) -> String {
+    let context = stdout.split_once("### CONTEXT\n").unwrap().1;
+    context.lines().next().unwrap().to_string()
+}
+
+pub fn get_context_value(stdout: &str) -> Value {
+    serde_json::from_str(&get_context(stdout)).unwrap()
+}
+
+// records a cassette for QUERY and swaps its response for the given fixture
+pub fn load_cassette(root: &Path, fixture: &str) {
+    let output = run(root, &["--record", &cassette_dir(root)]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries = fs::read_dir(cassette_dir(root))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<PathBuf>>();
+    assert_eq!(entries.len(), 1);
+
+    let mut cassette: Value =
+        serde_json::from_str(&fs::read_to_string(&entries[0]).unwrap()).unwrap();
+    cassette["response"] = Value::String(fs::read_to_string(fixture_path(fixture)).unwrap());
+    fs::write(&entries[0], cassette.to_string()).unwrap();
+}
+
+// minimal stand-in for ollama, answers every request with the given fixture and keeps the
+// request bodies around so tests can look at what was sent
+pub fn start_server(fixture: &str) -> (String, Arc<Mutex<Vec<String>>>) {
+    start_server_with(&[fixture])
+}
+
+// same as start_server, but answers with the fixtures in order, the last one is repeated once
+// they run out
+pub fn start_server_with(fixtures: &[&str]) -> (String, Arc<Mutex<Vec<String>>>) {
+    let bodies = fixtures
+        .iter()
+        .map(|fixture| fs::read_to_string(fixture_path(fixture)).unwrap())
+        .collect::<Vec<String>>();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/api/chat", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(vec![]));
+
+    let thread_requests = requests.clone();
+    thread::spawn(move || {
+        for (i, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let request = read_request(&mut stream);
+            thread_requests.lock().unwrap().push(request);
+            let body = &bodies[i.min(bodies.len() - 1)];
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+
+    (url, requests)
+}
+
+// reads the headers and then exactly content-length bytes of body
+fn read_request(stream: &mut TcpStream) -> String {
+    let mut reader = BufReader::new(stream);
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some(len) = line.to_lowercase().strip_prefix("content-length:") {
+            content_length = len.trim().parse().unwrap();
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    String::from_utf8(body).unwrap()
+}
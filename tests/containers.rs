@@ -0,0 +1,140 @@
+// docker is reached over a unix socket, there is no stand in for it elsewhere
+#![cfg(unix)]
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use common::*;
+
+mod common;
+
+const KUBE_CONFIG: &str = r#"
+apiVersion: v1
+kind: Config
+current-context: staging
+contexts:
+- name: staging
+  context:
+    cluster: staging-cluster
+    namespace: api
+- name: local
+  context:
+    cluster: kind-local
+users:
+- name: admin
+  user:
+    token: kube-admin-token-do-not-send
+"#;
+
+const CONTAINERS: &str = r#"[
+    {"Names": ["/shop-api-1"], "Image": "shop/api:1.4", "State": "running", "Status": "Up 2 hours", "Labels": {}},
+    {"Names": ["/k8s_api_api-7d9f-xk2p"], "Image": "shop/api:1.5", "State": "running", "Status": "Up 5 minutes", "Labels": {"io.kubernetes.pod.name": "api-7d9f-xk2p"}}
+]"#;
+
+// stand-in for the docker daemon, answers every request on the socket with the containers
+fn start_docker(root: &Path) -> PathBuf {
+    let socket = root.join("docker.sock");
+    _ = fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket).unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 || line.trim().is_empty() {
+                    break;
+                }
+            }
+
+            let (status, body) = match request_line.starts_with("GET /containers/json ") {
+                true => ("200 OK", CONTAINERS),
+                false => ("404 Not Found", "{}"),
+            };
+            write!(
+                stream,
+                "HTTP/1.0 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+
+    socket
+}
+
+fn write_kube_config(root: &Path) -> PathBuf {
+    let path = root.join("kubeconfig");
+    fs::write(&path, KUBE_CONFIG).unwrap();
+    path
+}
+
+#[test]
+fn kube_compose_and_docker_are_sent_with_the_prompt() {
+    let root = setup("kube_compose_and_docker_are_sent_with_the_prompt");
+    let kube_config = write_kube_config(&root);
+    let socket = start_docker(&root);
+    fs::write(
+        root.join("work").join("docker-compose.yml"),
+        "services:\n  api:\n    image: shop/api\n  db:\n    image: postgres:16\n",
+    )
+    .unwrap();
+
+    let output = run_with_env(
+        &root,
+        &["prompt", "restart", "the", "api", "pod"],
+        "",
+        &[
+            ("KUBECONFIG", kube_config.to_str().unwrap()),
+            ("DOCKER_HOST", &format!("unix://{}", socket.display())),
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    assert!(stdout.contains(
+        r#""kubernetes":{"contexts":[{"cluster":"staging-cluster","name":"staging","namespace":"api"},{"cluster":"kind-local","name":"local","namespace":"default"}],"current_context":"staging","namespaces":["api","default"]}"#
+    ), "{}", stdout);
+    assert!(stdout.contains(r#""compose_file":"docker-compose.yml""#));
+    assert!(stdout.contains(r#""services":["api","db"]"#));
+    assert!(stdout.contains(
+        r#"{"image":"shop/api:1.5","name":"k8s_api_api-7d9f-xk2p","pod":"api-7d9f-xk2p","status":"Up 5 minutes"}"#
+    ));
+    assert!(!stdout.contains("kube-admin-token-do-not-send"));
+}
+
+#[test]
+fn container_fields_are_offered_the_running_containers() {
+    let root = setup("container_fields_are_offered_the_running_containers");
+    let kube_config = write_kube_config(&root);
+    let socket = start_docker(&root);
+    let (url, _) = start_server("containers.json");
+
+    let output = run_with_env(
+        &root,
+        &["--backend", "ollama", "--url", &url, "--no-cache", QUERY],
+        "0\n0\n0\n",
+        &[
+            ("KUBECONFIG", kube_config.to_str().unwrap()),
+            ("DOCKER_HOST", &format!("unix://{}", socket.display())),
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    assert!(
+        stdout.contains("  0 - shop-api-1 (running shop/api:1.4, Up 2 hours)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("  1 - k8s_api_api-7d9f-xk2p (running shop/api:1.5, Up 5 minutes)"));
+    assert!(stdout.contains("  0 - api (namespace from the kube config)"));
+    assert!(stdout.contains("executing cmd echo restarting shop-api-1 in api"));
+}
@@ -0,0 +1,90 @@
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use std::path::Path;
+use std::time::Instant;
+
+use common::*;
+
+mod common;
+
+// the providers are shell scripts, so these only run on unix
+#[cfg(unix)]
+fn write_provider(root: &Path, name: &str, script: &str) {
+    let dir = root.join("home").join("providers");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(name), script).unwrap();
+    fs::set_permissions(dir.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn external_providers_are_merged_into_the_context() {
+    let root = setup("external_providers_are_merged_into_the_context");
+    write_provider(
+        &root,
+        "weather",
+        "#!/bin/sh\necho '{\"forecast\": \"sunny\"}'\n",
+    );
+    write_provider(&root, "slow", "#!/bin/sh\nsleep 5\necho '{}'\n");
+    fs::write(
+        root.join("home").join("context.toml"),
+        "[providers.slow]\ntimeout_ms = 200\n\n[providers.git]\nenabled = false\n",
+    )
+    .unwrap();
+
+    let start = Instant::now();
+    let output = run_command(&root, &["prompt", "list", "files"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(start.elapsed().as_secs() < 4);
+
+    let context = get_context(&stdout);
+    assert!(
+        context.contains("\"weather\":{\"forecast\":\"sunny\"}"),
+        "{}",
+        context
+    );
+    assert!(!context.contains("\"slow\""));
+    assert!(!context.contains("\"git\""));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("context provider slow timed out"));
+}
+
+#[test]
+fn built_in_providers_stay_within_their_budget() {
+    let history = (0..100)
+        .map(|i| {
+            format!(
+                r#"{{"dir": "/home/user", "cmd": "echo {}", "datetime": "2024-11-04 06:13:34"}}"#,
+                i
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    let root = setup_with_history(
+        "built_in_providers_stay_within_their_budget",
+        &format!("[{}]", history),
+    );
+    fs::write(root.join("work").join("Cargo.toml"), "[package]\n").unwrap();
+    fs::create_dir_all(root.join("home")).unwrap();
+    fs::write(
+        root.join("home").join("context.toml"),
+        "[providers.history]\ntoken_budget = 50\n",
+    )
+    .unwrap();
+
+    let output = run_command(&root, &["prompt", "list", "files"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let context = get_context(&stdout);
+    let project = format!(
+        r#""project":{{"root":"{}","types":[{{"manifest":"Cargo.toml","tool":"cargo","type":"rust"}}]}}"#,
+        root.join("work").display()
+    );
+    assert!(context.contains(&project), "{}", context);
+    // the most recent commands are the ones that are kept
+    assert!(context.contains("\"cmd\":\"echo 99\""));
+    assert!(!context.contains("\"cmd\":\"echo 0\""));
+}
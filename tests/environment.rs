@@ -0,0 +1,67 @@
+use std::fs;
+use std::process::Command;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn environment_is_sent_with_the_prompt() {
+    let root = setup("environment_is_sent_with_the_prompt");
+    let (url, requests) = start_server("well_formed.json");
+
+    let output = run_with_stdin(&root, &["--backend", "ollama", "--url", &url, QUERY], "");
+    assert!(output.status.success());
+
+    let request: Value = serde_json::from_str(&requests.lock().unwrap()[0]).unwrap();
+    let system_prompt = request["messages"][0]["content"].as_str().unwrap();
+    assert!(system_prompt.contains(&format!("\"os\":\"{}\"", std::env::consts::OS)));
+    assert!(system_prompt.contains("Only use flags and syntax that work with these exact variants"));
+    assert!(!system_prompt.contains("macOS; some packages"));
+
+    if let Ok(os_release) = fs::read_to_string("/etc/os-release") {
+        let pretty_name = os_release
+            .lines()
+            .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+            .map(|name| name.trim_matches('"'));
+        if let Some(pretty_name) = pretty_name {
+            assert!(
+                system_prompt.contains(&format!("The user is on {}", pretty_name)),
+                "{}",
+                system_prompt
+            );
+        }
+    }
+}
+
+#[test]
+fn history_defaults_to_the_data_dir() {
+    let root = setup("history_defaults_to_the_data_dir");
+    fs::create_dir_all(root.join("home")).unwrap();
+
+    // without a history file there is nothing to search
+    let output = Command::new(env!("CARGO_BIN_EXE_rust-cli"))
+        .current_dir(root.join("work"))
+        .env("ZLI_HOME", root.join("home"))
+        .args(["--offline", "cargo", "build"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("no suggestions"));
+
+    fs::copy(
+        root.join("history.json"),
+        root.join("home").join("history.json"),
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rust-cli"))
+        .current_dir(root.join("work"))
+        .env("ZLI_HOME", root.join("home"))
+        .args(["--offline", "cargo", "build"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("    cargo build"), "{}", stdout);
+}
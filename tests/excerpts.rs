@@ -0,0 +1,180 @@
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::symlink;
+use std::path::Path;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+const PACKAGE_JSON: &str = r#"{
+  "name": "shop",
+  "scripts": {
+    "dev": "vite --port 3000",
+    "build": "vite build"
+  }
+}
+"#;
+
+fn get_files(stdout: &str) -> Vec<Value> {
+    get_context_value(stdout)["files"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn write_project(work: &Path) {
+    fs::write(work.join("package.json"), PACKAGE_JSON).unwrap();
+    fs::write(
+        work.join("Dockerfile"),
+        "FROM node:20\nLABEL org.opencontainers.image.title=shop\n",
+    )
+    .unwrap();
+    fs::write(
+        work.join("logo.png"),
+        b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR",
+    )
+    .unwrap();
+    fs::write(work.join(".env"), "STRIPE_KEY=sk_live_do_not_send\n").unwrap();
+    fs::write(
+        work.join("deploy.conf"),
+        "host = prod.example.com\npassword = hunter2hunter2\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn manifests_and_mentioned_files_are_excerpted() {
+    let root = setup("manifests_and_mentioned_files_are_excerpted");
+    write_project(&root.join("work"));
+
+    let output = run_command(
+        &root,
+        &[
+            "prompt",
+            "build",
+            "the",
+            "image",
+            "from",
+            "deploy.conf",
+            "logo.png",
+            ".env",
+            "/etc/passwd",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    // the file named in the query, then the manifest the query is about, then the others
+    let files = get_files(&stdout);
+    let paths = files
+        .iter()
+        .map(|file| file["path"].as_str().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(paths, ["deploy.conf", "Dockerfile", "package.json"]);
+    assert_eq!(files[2]["content"], PACKAGE_JSON);
+    assert!(files[2].get("truncated").is_none());
+
+    // what gets sent goes through the redaction like the rest of the prompt
+    assert!(files[0]["content"]
+        .as_str()
+        .unwrap()
+        .starts_with("host = prod.example.com\npassword = <redacted_"));
+    assert!(!stdout.contains("hunter2hunter2"));
+    assert!(!stdout.contains("sk_live_do_not_send"));
+    assert!(!stdout.contains("root:x:0:0"));
+}
+
+#[test]
+fn excerpts_stay_within_their_budget() {
+    let root = setup("excerpts_stay_within_their_budget");
+    let work = root.join("work");
+    write_project(&work);
+    let makefile = (0..200)
+        .map(|i| format!("target{}:\n\techo {}\n", i, i))
+        .collect::<String>();
+    fs::write(work.join("Makefile"), &makefile).unwrap();
+    fs::create_dir_all(root.join("home")).unwrap();
+    fs::write(
+        root.join("home").join("context.toml"),
+        "[providers.files]\ntoken_budget = 150\n",
+    )
+    .unwrap();
+
+    let output = run_command(&root, &["prompt", "make", "target", "42"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let files = get_files(&stdout);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(files[0]["path"], "Makefile");
+    assert_eq!(files[0]["truncated"], true);
+    let content = files[0]["content"].as_str().unwrap();
+    assert!(content.starts_with("target0:\n\techo 0\n"));
+    assert!(content.ends_with('\n'));
+    assert!(serde_json::to_string(&files).unwrap().len() <= 150 * 4);
+}
+
+// the linked file is a unix symlink
+#[cfg(unix)]
+#[test]
+fn secrets_in_manifests_and_linked_files_are_not_sent() {
+    let root = setup("secrets_in_manifests_and_linked_files_are_not_sent");
+    let work = root.join("work");
+    fs::write(
+        work.join("config.json"),
+        "{\n  \"db_password\": \"hunter2\",\n  \"password\": \"swordfish\",\n  \"port\": 5432\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        work.join("settings.toml"),
+        "[db]\napi_key = \"tomlsecret1\"\n",
+    )
+    .unwrap();
+    fs::write(work.join("config.yaml"), "db:\n  password: yamlsecret1\n").unwrap();
+    fs::write(root.join("aws_credentials"), "aws_secret=outsidesecret1\n").unwrap();
+    symlink(root.join("aws_credentials"), work.join("notes.txt")).unwrap();
+    fs::write(work.join("credentials.json"), "{}\n").unwrap();
+    fs::write(work.join("id_ecdsa"), "not a key\n").unwrap();
+    fs::write(work.join(".npmrc"), "//registry/:_auth=npmsecret1\n").unwrap();
+
+    let output = run_command(
+        &root,
+        &[
+            "prompt",
+            "read",
+            "config.json",
+            "settings.toml",
+            "config.yaml",
+            "notes.txt",
+            "credentials.json",
+            "id_ecdsa",
+            ".npmrc",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let files = get_files(&stdout);
+    let paths = files
+        .iter()
+        .map(|file| file["path"].as_str().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(paths, ["config.json", "settings.toml", "config.yaml"]);
+    assert!(files[0]["content"]
+        .as_str()
+        .unwrap()
+        .contains("\"port\": 5432"));
+    for secret in [
+        "hunter2",
+        "swordfish",
+        "tomlsecret1",
+        "yamlsecret1",
+        "outsidesecret1",
+        "npmsecret1",
+    ] {
+        assert!(!stdout.contains(secret), "{} in {}", secret, stdout);
+    }
+}
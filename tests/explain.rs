@@ -0,0 +1,128 @@
+use std::fs;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn explain_renders_each_stage() {
+    let root = setup("explain_renders_each_stage");
+    let (url, requests) = start_server("explain.json");
+
+    let output = run_command(
+        &root,
+        &[
+            "--backend",
+            "ollama",
+            "--url",
+            &url,
+            "explain",
+            "find",
+            ".",
+            "-name",
+            "'*.tmp'",
+            "|",
+            "xargs",
+            "rm",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("stage 1 - find . -name '*.tmp'"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("    -name '*.tmp'  only match files ending in .tmp"));
+    assert!(stdout.contains("stage 2 - xargs rm"));
+    assert!(stdout.contains("    - deletes the matched files"));
+    assert!(stdout.contains("  risk: HIGH - deleted files cannot be recovered"));
+
+    let requests = requests.lock().unwrap();
+    let request: Value = serde_json::from_str(&requests[0]).unwrap();
+    assert_eq!(
+        request["messages"][1]["content"],
+        "command -> find . -name '*.tmp' | xargs rm\nstage 1 -> find . -name '*.tmp'\nstage 2 -> xargs rm"
+    );
+}
+
+#[test]
+fn explain_keeps_quoted_and_redirected_operators_in_one_stage() {
+    let root = setup("explain_keeps_quoted_and_redirected_operators_in_one_stage");
+    let (url, requests) = start_server("explain.json");
+
+    run_command(
+        &root,
+        &[
+            "--backend",
+            "ollama",
+            "--url",
+            &url,
+            "explain",
+            "grep 'a|b' log.txt 2>&1 && echo done; ls",
+        ],
+    );
+
+    let requests = requests.lock().unwrap();
+    let request: Value = serde_json::from_str(&requests[0]).unwrap();
+    let content = request["messages"][1]["content"].as_str().unwrap();
+    assert!(
+        content.contains("stage 1 -> grep 'a|b' log.txt 2>&1\n"),
+        "{}",
+        content
+    );
+    assert!(content.contains("stage 2 -> echo done\n"));
+    assert!(content.ends_with("stage 3 -> ls"));
+}
+
+#[test]
+fn explain_sends_the_schema_unredacted() {
+    let root = setup("explain_sends_the_schema_unredacted");
+    let (url, requests) = start_server("explain.json");
+
+    let output = run_command(
+        &root,
+        &[
+            "--backend",
+            "ollama",
+            "--url",
+            &url,
+            "--show-context",
+            "explain",
+            "ls",
+            "-la",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let schema = fs::read_to_string("prompts/explain.schema.json").unwrap();
+    assert!(stdout.contains(schema.trim()), "{}", stdout);
+    assert!(!stdout.contains("<redacted_"), "{}", stdout);
+
+    let requests = requests.lock().unwrap();
+    let request: Value = serde_json::from_str(&requests[0]).unwrap();
+    let system = request["messages"][0]["content"].as_str().unwrap();
+    assert!(system.contains(schema.trim()), "{}", system);
+}
+
+#[test]
+fn explain_reports_a_malformed_response() {
+    let root = setup("explain_reports_a_malformed_response");
+    let (url, _) = start_server("malformed.json");
+
+    let output = run_command(
+        &root,
+        &["--backend", "ollama", "--url", &url, "explain", "ls", "-la"],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(
+        stderr.contains("Unable to parse the model response: EOF while parsing"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
@@ -0,0 +1,82 @@
+use std::fs;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+fn load_choices(root: &std::path::Path) -> Vec<Value> {
+    fs::read_to_string(root.join("home").join("feedback.jsonl"))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn picked_suggestions_are_used_as_examples() {
+    let root = setup("picked_suggestions_are_used_as_examples");
+    let (url, requests) = start_server("fix.json");
+    let args = ["--backend", "ollama", "--url", &url, "--no-cache", QUERY];
+
+    let output = run_with_stdin(&root, &args, "0\nhi\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("executing cmd echo hi > fixed.txt"));
+
+    let choices = load_choices(&root);
+    assert_eq!(choices.len(), 1);
+    assert_eq!(choices[0]["query"], QUERY);
+    assert!(choices[0]["cwd"].as_str().unwrap().ends_with("work"));
+    assert_eq!(choices[0]["picked"]["reasoning"], "psuh is a typo of push");
+    assert_eq!(choices[0]["values"][0]["key"], "greeting");
+    assert_eq!(choices[0]["values"][0]["value"], "hi");
+
+    // the value typed last time is offered as a suggestion, and the pick is sent as an example
+    let output = run_with_stdin(&root, &args, "0\n1\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("  1 - hi (you used this 1 time(s) before)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("executing cmd echo hi > fixed.txt"));
+
+    let requests = requests.lock().unwrap();
+    let first: Value = serde_json::from_str(&requests[0]).unwrap();
+    let second: Value = serde_json::from_str(&requests[1]).unwrap();
+    assert!(!first["messages"][0]["content"]
+        .as_str()
+        .unwrap()
+        .contains("For similar queries in the past"));
+    let system_prompt = second["messages"][0]["content"].as_str().unwrap();
+    assert!(system_prompt
+        .contains("### EXAMPLES\nquery: run the tests\noutput: {\"response\":[{\"reasoning\":\"psuh is a typo of push\""));
+}
+
+#[test]
+fn typed_values_are_offered_after_the_model_suggestions() {
+    let root = setup("typed_values_are_offered_after_the_model_suggestions");
+    let (url, _) = start_server("fix.json");
+    let args = ["--backend", "ollama", "--url", &url, "--no-cache", QUERY];
+
+    run_with_stdin(&root, &args, "0\n0\n");
+    run_with_stdin(&root, &args, "0\nhey\n");
+    run_with_stdin(&root, &args, "0\nhey\n");
+
+    let output = run_with_stdin(&root, &args, "");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 - psuh is a typo of push"));
+    let output = run_with_stdin(&root, &args, "0\n0\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "  0 - hello (a friendly default)\n  1 - hey (you used this 2 time(s) before)"
+        ),
+        "{}",
+        stdout
+    );
+
+    let choices = load_choices(&root);
+    assert_eq!(choices.len(), 5);
+    assert!(choices[3]["picked"].is_null());
+    assert_eq!(choices[3]["skipped"][0], "psuh is a typo of push");
+}
@@ -0,0 +1,77 @@
+// the tree has a symlink and an executable script, which only exist like this on unix
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::Path;
+
+use common::*;
+
+mod common;
+
+// a data dir too big to list, an ignored node_modules, a dotfile, a symlink and a script
+fn write_tree(work: &Path) {
+    fs::create_dir_all(work.join("data")).unwrap();
+    for i in 0..30 {
+        fs::write(work.join("data").join(format!("{}.json", i)), "{}").unwrap();
+    }
+    fs::write(work.join("data").join("notes.csv"), "a,b").unwrap();
+    fs::create_dir_all(work.join("node_modules").join("left-pad")).unwrap();
+    fs::write(work.join(".gitignore"), "node_modules\n").unwrap();
+    fs::write(work.join(".env"), "DEBUG=1\n").unwrap();
+    fs::create_dir_all(work.join("src")).unwrap();
+    fs::write(work.join("src").join("lib.rs"), "").unwrap();
+    symlink("src", work.join("current")).unwrap();
+    fs::write(work.join("deploy.sh"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(work.join("deploy.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn filesystem_is_walked_and_marked() {
+    let root = setup("filesystem_is_walked_and_marked");
+    write_tree(&root.join("work"));
+
+    let output = run_command(&root, &["prompt", "list", "files"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let context = get_context(&stdout);
+    assert!(
+        context.contains(r#""name":"data","summary":"30 .json files, 1 .csv file"}"#),
+        "{}",
+        context
+    );
+    assert!(!context.contains("data/0.json"));
+    assert!(!context.contains("node_modules"));
+    assert!(context.contains(r#"{"hidden":true,"kind":"file","modified":"#));
+    assert!(context.contains(r#""name":".env","size":8}"#));
+    assert!(context.contains(r#""name":"current","target":"src"}"#));
+    assert!(context.contains(r#"{"kind":"symlink","modified":"#));
+    assert!(context.contains(r#"{"executable":true,"extension":"sh","kind":"file","modified":"#));
+    assert!(context.contains(r#""name":"src/lib.rs","size":0}"#));
+}
+
+#[test]
+fn walker_limits_come_from_the_config() {
+    let root = setup("walker_limits_come_from_the_config");
+    write_tree(&root.join("work"));
+    fs::create_dir_all(root.join("home")).unwrap();
+    fs::write(
+        root.join("home").join("context.toml"),
+        "[providers.filesystem]\nmax_depth = 1\nmax_entries = 4\ngitignore = false\n",
+    )
+    .unwrap();
+
+    let output = run_command(&root, &["prompt", "list", "files"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    // .env .gitignore current data deploy.sh main.rs node_modules src, in name order
+    let context = get_context(&stdout);
+    assert!(context.contains(r#""name":"data"}"#), "{}", context);
+    assert!(!context.contains("deploy.sh"));
+    assert!(!context.contains("src/lib.rs"));
+    assert!(
+        context.contains(r#"{"kind":"summary","name":".","summary":"4 more entries not listed"}"#)
+    );
+}
@@ -0,0 +1,126 @@
+use std::fs;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+fn get_user_message(request: &str) -> String {
+    let request: Value = serde_json::from_str(request).unwrap();
+    request["messages"][1]["content"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn fix_reruns_the_command_from_the_hook() {
+    let root = setup("fix_reruns_the_command_from_the_hook");
+    fs::create_dir_all(root.join("home")).unwrap();
+    fs::write(
+        root.join("home").join("last_command"),
+        format!(
+            "1\n{}\ncat does-not-exist.txt\n",
+            root.join("work").display()
+        ),
+    )
+    .unwrap();
+    let (url, requests) = start_server("fix.json");
+
+    // --rerun is the consent, so there is nothing to ask
+    let output = run_command(
+        &root,
+        &["--backend", "ollama", "--url", &url, "fix", "--rerun"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("re-run"));
+    assert!(stdout.contains("0 - psuh is a typo of push"), "{}", stdout);
+
+    let message = get_user_message(&requests.lock().unwrap()[0]);
+    assert!(
+        message.contains("command -> cat does-not-exist.txt\n"),
+        "{}",
+        message
+    );
+    assert!(message.contains("exit status -> 1\n"));
+    assert!(message.contains("does-not-exist.txt: No such file or directory"));
+}
+
+#[test]
+fn fix_reruns_last_history_entry() {
+    let work = setup("fix_reruns_last_history_entry").join("work");
+    let history = format!(
+        r#"[{{"dir": "{}", "cmd": "cat does-not-exist.txt", "datetime": "2024-11-04 06:13:34"}}]"#,
+        work.display()
+    );
+    let root = setup_with_history("fix_reruns_last_history_entry", &history);
+    let (url, requests) = start_server("fix.json");
+
+    let output = run_with_stdin(&root, &["--backend", "ollama", "--url", &url, "fix"], "y\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("re-run `cat does-not-exist.txt`"));
+
+    let message = get_user_message(&requests.lock().unwrap()[0]);
+    assert!(message.contains("exit status -> 1\n"), "{}", message);
+    assert!(message.contains("does-not-exist.txt: No such file or directory"));
+}
+
+#[test]
+fn fix_without_rerun_sends_unknown_status() {
+    let root = setup("fix_without_rerun_sends_unknown_status");
+    let (url, requests) = start_server("fix.json");
+
+    run_with_stdin(&root, &["--backend", "ollama", "--url", &url, "fix"], "n\n");
+
+    let message = get_user_message(&requests.lock().unwrap()[0]);
+    assert!(message.contains("command -> cargo build\n"), "{}", message);
+    assert!(message.contains("exit status -> unknown\n"));
+}
+
+#[test]
+fn selected_suggestion_is_executed_until_a_command_fails() {
+    let root = setup("selected_suggestion_is_executed_until_a_command_fails");
+    let (url, _) = start_server("fix.json");
+
+    // pick the first suggestion, then the first suggested value for <greeting>
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, "fix"],
+        "n\n0\n0\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("executing cmd echo hello > fixed.txt"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("not running the rest"));
+    assert_eq!(
+        fs::read_to_string(root.join("work").join("fixed.txt")).unwrap(),
+        "hello\n"
+    );
+    assert!(!root.join("work").join("never.txt").exists());
+}
+
+#[test]
+fn hook_prints_shell_script() {
+    let root = setup("hook_prints_shell_script");
+
+    let zsh = run_command(&root, &["hook", "zsh"]);
+    assert!(String::from_utf8_lossy(&zsh.stdout).contains("add-zsh-hook precmd _zli_precmd"));
+
+    let bash = run_command(&root, &["hook", "bash"]);
+    let bash = String::from_utf8_lossy(&bash.stdout);
+    assert!(bash.contains("PROMPT_COMMAND=\"_zli_precmd"));
+    assert!(
+        bash.contains(" history add --dir \"$PWD\" -- \"$2\""),
+        "{}",
+        bash
+    );
+
+    // the stderr of the interactive shell is never redirected
+    assert!(!bash.contains("exec 2>"), "{}", bash);
+}
@@ -0,0 +1,87 @@
+use common::*;
+
+mod common;
+
+const HISTORY: &str = r#"[
+    {"dir": "/somewhere/else", "cmd": "ls -la", "datetime": "2024-11-01 10:00:00"},
+    {"dir": "/somewhere/else", "cmd": "scp ./data.json root@172.186.26.5:/root/destination", "datetime": "2024-11-02 10:00:00"},
+    {"dir": "/somewhere/else", "cmd": "git commit -m 'fix the parser'", "datetime": "2024-11-03 10:00:00"},
+    {"dir": "/somewhere/else", "cmd": "git commit -m \"bump version\"", "datetime": "2024-11-04 10:00:00"},
+    {"dir": "/somewhere/else", "cmd": "curl https://example.com/health", "datetime": "2024-11-04 11:00:00"}
+]"#;
+
+fn get_suggestions_log(stderr: &str) -> &str {
+    let start = stderr.find("suggestions are").unwrap();
+    &stderr[start..]
+}
+
+#[test]
+fn offline_turns_variable_parts_into_placeholders() {
+    let root = setup_with_history("offline_turns_variable_parts_into_placeholders", HISTORY);
+
+    let output = run_command(&root, &["--offline", "scp", "json"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+
+    let suggestions = get_suggestions_log(&stderr);
+    assert!(
+        suggestions.contains(r#""cmd":"scp <path> <host>:<remote_path>""#),
+        "{}",
+        suggestions
+    );
+    assert!(suggestions.contains(r#""value":"root@172.186.26.5""#));
+    assert!(suggestions.contains(r#""value":"/root/destination""#));
+}
+
+#[test]
+fn offline_merges_commands_that_only_differ_in_placeholders() {
+    let root = setup_with_history(
+        "offline_merges_commands_that_only_differ_in_placeholders",
+        HISTORY,
+    );
+
+    let output = run_command(&root, &["--offline", "commit"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let suggestions = get_suggestions_log(&stderr);
+    assert_eq!(
+        suggestions
+            .matches(r#""cmd":"git commit -m \"<message>\"""#)
+            .count(),
+        1,
+        "{}",
+        suggestions
+    );
+    assert!(suggestions.contains("run 2 time(s)"), "{}", suggestions);
+    assert!(!suggestions.contains("ls -la"));
+}
+
+#[test]
+fn offline_without_matches() {
+    let root = setup_with_history("offline_without_matches", HISTORY);
+
+    let output = run_command(&root, &["--offline", "kubectl"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success());
+    assert!(get_suggestions_log(&stderr).contains("[]"), "{}", stderr);
+}
+
+#[test]
+fn unreachable_model_falls_back_to_history() {
+    let root = setup_with_history("unreachable_model_falls_back_to_history", HISTORY);
+
+    // nothing listens on port 9, so the connection is refused right away
+    let output = run_command(
+        &root,
+        &[
+            "--backend",
+            "ollama",
+            "--url",
+            "http://127.0.0.1:9/api/chat",
+            "health",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("Backend unavailable"), "{}", stderr);
+    assert!(get_suggestions_log(&stderr).contains(r#""cmd":"curl <url>""#));
+}
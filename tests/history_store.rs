@@ -0,0 +1,191 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::*;
+
+mod common;
+
+// the second record is cut off halfway, the rest has to survive it
+const OLD_HISTORY: &str = r#"[
+    {"dir": "/home/user/shop", "cmd": "git pull", "datetime": "2024-11-03 09:00:00"},
+    {"dir": "/home/user/shop", "cmd": "cargo bu
+    {"dir": "/home/user/shop", "cmd": "cargo build --release", "datetime": "2024-11-04 06:13:34"},
+    {"dir": "/home/user/blog", "cmd": "git push", "datetime": "2024-11-04 18:30:00"},
+    {"dir": "/home/user/shop", "cmd": "cargo build --release", "datetime": "2024-11-05 08:00:00"},
+    {"dir": "/home/user/shop", "cmd": 42, "datetime": "2024-11-05 09:00:00"}
+]"#;
+
+// without --history, so the store in the data dir is what gets used
+fn run_without_history(root: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rust-cli"))
+        .current_dir(root.join("work"))
+        .env("ZLI_HOME", root.join("home"))
+        .args(["-l", "debug"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn import(root: &Path) -> String {
+    let output = run_command(
+        root,
+        &["history", "import", root.join("old.json").to_str().unwrap()],
+    );
+    assert!(output.status.success());
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn list(root: &Path, args: &[&str]) -> String {
+    let mut args = args.to_vec();
+    args.insert(0, "list");
+    args.insert(0, "history");
+    let output = run_command(root, &args);
+    assert!(output.status.success());
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn imported_history_is_looked_up_through_the_index() {
+    let root = setup("imported_history_is_looked_up_through_the_index");
+    fs::write(root.join("old.json"), OLD_HISTORY).unwrap();
+
+    assert_eq!(
+        import(&root),
+        "imported 4 records, skipped 2 broken ones, 4 in the store\n"
+    );
+    // importing again doesn't add anything twice
+    assert_eq!(
+        import(&root),
+        "imported 0 records, skipped 2 broken ones, 4 in the store\n"
+    );
+
+    assert_eq!(
+        list(&root, &["--dir", "/home/user/blog"]),
+        "2024-11-04 18:30:00  /home/user/blog  git push\n"
+    );
+    assert_eq!(
+        list(&root, &["--since", "2024-11-04", "--until", "2024-11-04"]),
+        "2024-11-04 06:13:34  /home/user/shop  cargo build --release\n2024-11-04 18:30:00  /home/user/blog  git push\n"
+    );
+    assert_eq!(
+        list(&root, &["--prefix", "git ", "--dir", "/home/user/shop"]),
+        "2024-11-03 09:00:00  /home/user/shop  git pull\n"
+    );
+    assert_eq!(
+        list(&root, &["--counts"]),
+        "    2  cargo build --release\n    1  git pull\n    1  git push\n"
+    );
+}
+
+#[test]
+fn broken_records_in_the_log_are_skipped() {
+    let root = setup("broken_records_in_the_log_are_skipped");
+    fs::write(root.join("old.json"), OLD_HISTORY).unwrap();
+    import(&root);
+
+    // a half written line, then a record appended by something else after it
+    let work = root.join("work").to_str().unwrap().to_string();
+    let mut log = fs::OpenOptions::new()
+        .append(true)
+        .open(root.join("home").join("history.jsonl"))
+        .unwrap();
+    writeln!(log, "{{\"dir\": \"/home/user/shop\", \"cmd\": \"make").unwrap();
+    writeln!(
+        log,
+        "{}",
+        serde_json::json!({"dir": work, "cmd": "make test", "datetime": "2024-11-06 10:00:00"})
+    )
+    .unwrap();
+
+    let output = run_without_history(&root, &["prompt", "list", "files"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("skipped 1 broken records in the history log"));
+
+    let context = get_context_value(&stdout);
+    let cmds = context["history"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|history| history["cmd"].as_str().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        cmds,
+        [
+            "git pull",
+            "cargo build --release",
+            "git push",
+            "cargo build --release",
+            "make test"
+        ]
+    );
+    assert_eq!(
+        list(&root, &["--dir", &work]),
+        format!("2024-11-06 10:00:00  {}  make test\n", work)
+    );
+
+    // a broken history.json given with --history still gives the records that are fine
+    fs::write(root.join("history.json"), OLD_HISTORY).unwrap();
+    let output = run_command(&root, &["prompt", "list", "files"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains(r#""cmd":"git push""#));
+    assert!(!stdout.contains("make test"));
+}
+
+#[test]
+fn the_hook_adds_to_the_log_and_a_replaced_log_is_reindexed() {
+    let root = setup("the_hook_adds_to_the_log_and_a_replaced_log_is_reindexed");
+    fs::write(root.join("old.json"), OLD_HISTORY).unwrap();
+    import(&root);
+
+    // history.json is only an import source, the shell adds to the store through the hook
+    fs::write(
+        root.join("home").join("history.json"),
+        r#"[{"dir": "/home/user/shop", "cmd": "make", "datetime": "2024-11-07 19:00:00"}]"#,
+    )
+    .unwrap();
+    let hook = run_command(&root, &["hook", "bash"]);
+    let hook = String::from_utf8_lossy(&hook.stdout);
+    let output = Command::new("bash")
+        .current_dir(root.join("work"))
+        .env("ZLI_HOME", root.join("home"))
+        .arg("-c")
+        .arg(format!(
+            "{}\n_zli_save_last_command 0 'hugo serve --port 1313'",
+            hook
+        ))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let work = root.join("work").to_str().unwrap().to_string();
+    let added = list(&root, &["--dir", &work]);
+    assert!(
+        added.ends_with(&format!("  {}  hugo serve --port 1313\n", work)),
+        "{}",
+        added
+    );
+    assert_eq!(list(&root, &["--prefix", "make"]), "");
+    assert_eq!(list(&root, &[]).lines().count(), 5);
+
+    // a log swapped for another one that is just as long isn't read through the old index
+    let log = root.join("home").join("history.jsonl");
+    let len = fs::metadata(&log).unwrap().len() as usize;
+    let record = r#"{"dir":"/srv","cmd":"ls","datetime":"2024-12-01 00:00:00"}"#;
+    let mut replaced = format!("{}\n", record);
+    while replaced.len() < len {
+        replaced.push_str(&format!("{}\n", record));
+    }
+    let records = replaced.lines().count();
+    fs::write(&log, replaced).unwrap();
+    let counts = list(&root, &["--counts"]);
+    assert!(
+        counts.starts_with(&format!("{:>5}  ls\n", records)),
+        "{}",
+        counts
+    );
+}
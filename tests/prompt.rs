@@ -0,0 +1,148 @@
+use std::fs;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn prompt_renders_the_built_in_templates() {
+    let root = setup("prompt_renders_the_built_in_templates");
+
+    let output = run_command(&root, &["prompt", "push", "to", "git"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.starts_with("prompt version 10\n"), "{}", stdout);
+    assert!(stdout.contains("### SCHEMA\n{\n  \"response\": [\n"));
+    assert!(stdout.contains("\"cmd\":\"cargo build\""));
+    assert!(!stdout.contains("{{"));
+    assert!(stdout.ends_with("user:\npush to git\n"));
+}
+
+#[test]
+fn prompt_keeps_the_examples_and_lists_on_their_own_lines() {
+    let root = setup("prompt_keeps_the_examples_and_lists_on_their_own_lines");
+
+    let output = run_command(&root, &["prompt", "push", "to", "git"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "some examples of chains\n    git add -A; git commit -m <message>; git push -> this can be made into a chain of 3 commands\n        command 1 -> git add -A\n        command 2 -> git commit -m <message>\n        command 3 -> git push\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("assumptions:\n    1) The user is lazy"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\n    2) Assume the user"), "{}", stdout);
+    // the prose around them is still joined into one line per paragraph
+    assert!(
+        stdout.contains("can be of 2 types \"chain\" and \"not chain\" A chain suggestion"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn edited_templates_are_used_and_versioned() {
+    let root = setup("edited_templates_are_used_and_versioned");
+
+    let init = run_command(&root, &["prompt", "--init"]);
+    let init = String::from_utf8_lossy(&init.stdout);
+    assert_eq!(init.lines().count(), 6, "{}", init);
+    assert!(init.contains("system.j2"));
+    let again = run_command(&root, &["prompt", "--init"]);
+    assert!(String::from_utf8_lossy(&again.stdout).contains("already in place"));
+
+    fs::write(
+        root.join("home").join("prompts").join("system.j2"),
+        "Suggest commands for {{ os.os }}. {{ environment }} Context: {{ context }}",
+    )
+    .unwrap();
+
+    let output = run_command(&root, &["prompt", "push", "to", "git"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("prompt version 10-custom-"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(&format!(
+        "Suggest commands for {}. The user is on",
+        std::env::consts::OS
+    )));
+    // a template that places the context itself doesn't get it a second time
+    assert!(stdout.contains(" Context: {\""), "{}", stdout);
+    assert!(!stdout.contains("### CONTEXT"), "{}", stdout);
+    assert!(stdout.contains("### SCHEMA"), "{}", stdout);
+
+    // responses are tagged with the version of the templates they were for
+    let (url, requests) = start_server("well_formed.json");
+    let output = run_with_stdin(&root, &["--backend", "ollama", "--url", &url, QUERY], "");
+    assert!(output.status.success());
+    let request: Value = serde_json::from_str(&requests.lock().unwrap()[0]).unwrap();
+    assert!(request["messages"][0]["content"]
+        .as_str()
+        .unwrap()
+        .starts_with("Suggest commands for"));
+
+    let stats = fs::read_to_string(root.join("home").join("stats.jsonl")).unwrap();
+    let usage: Value = serde_json::from_str(stats.lines().next().unwrap()).unwrap();
+    assert!(usage["prompt_version"]
+        .as_str()
+        .unwrap()
+        .starts_with("10-custom-"));
+
+    let cache_entry = fs::read_dir(root.join("home").join("cache"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let cache_entry: Value =
+        serde_json::from_str(&fs::read_to_string(cache_entry).unwrap()).unwrap();
+    assert_eq!(cache_entry["prompt_version"], usage["prompt_version"]);
+}
+
+#[test]
+fn broken_templates_are_reported() {
+    let root = setup("broken_templates_are_reported");
+    fs::create_dir_all(root.join("home").join("prompts")).unwrap();
+    fs::write(
+        root.join("home").join("prompts").join("system.j2"),
+        "{{ no_such_variable }}",
+    )
+    .unwrap();
+
+    let output = run_command(&root, &["prompt", "push"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("unable to render prompt template system.j2"));
+}
+
+#[test]
+fn data_sections_keep_their_whitespace() {
+    let root = setup("data_sections_keep_their_whitespace");
+    fs::write(root.join("work").join("a  b.txt"), "").unwrap();
+
+    let output = run_command(&root, &["prompt", "list", "files"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    // the instructions are one paragraph per line apart from the indented examples, the data is
+    // passed along untouched
+    let (instructions, sections) = stdout.split_once("\n\n### SCHEMA\n").unwrap();
+    assert!(instructions
+        .lines()
+        .filter(|line| !line.starts_with(' '))
+        .all(|line| !line.contains("  ")));
+    assert!(instructions.contains("\n\n"));
+    assert!(sections.contains("      \"commands\": [\n"));
+    let context = sections.split_once("### CONTEXT\n").unwrap().1;
+    assert!(context.contains("\"name\":\"a  b.txt\""), "{}", context);
+}
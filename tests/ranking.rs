@@ -0,0 +1,57 @@
+use common::*;
+
+mod common;
+
+const HISTORY: &str = r#"[
+    {"dir": "/home/user/project", "cmd": "git commit -m 'fix the parser'", "datetime": "2024-11-03 10:00:00"},
+    {"dir": "/home/user/project", "cmd": "git push", "datetime": "2024-11-04 10:00:00"}
+]"#;
+
+#[test]
+fn suggestions_are_deduplicated_and_ranked() {
+    let root = setup_with_history("suggestions_are_deduplicated_and_ranked", HISTORY);
+    let (url, _) = start_server("unranked.json");
+
+    let output = run_with_stdin(&root, &["--backend", "ollama", "--url", &url, QUERY], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let listed = stdout
+        .lines()
+        .filter(|line| !line.starts_with(' ') && line.contains(" - "))
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        listed,
+        vec![
+            "0 - commit the changes",
+            "1 - echo one",
+            "2 - echo two",
+            "3 - echo three",
+            "4 - delete everything",
+        ],
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("empty command"));
+    assert!(!stdout.contains("commit with a message"));
+    assert!(!stdout.contains("not-a-real-binary-xyz"));
+}
+
+#[test]
+fn merged_suggestions_keep_every_field_suggestion() {
+    let root = setup_with_history("merged_suggestions_keep_every_field_suggestion", HISTORY);
+    let (url, _) = start_server("unranked.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, QUERY],
+        "0\n1\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("  0 - nits (small fixes)\n  1 - wip (work in progress)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("executing cmd git commit -m wip"));
+}
@@ -0,0 +1,87 @@
+use std::fs;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+const HISTORY: &str = r#"[
+    {"dir": "/home/user", "cmd": "export AWS_SECRET_ACCESS_KEY=wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY", "datetime": "2024-11-04 06:13:34"},
+    {"dir": "/home/user", "cmd": "curl -H \"Authorization: Bearer abc123def456\" https://api.example.com", "datetime": "2024-11-04 06:13:34"},
+    {"dir": "/home/user", "cmd": "mysql -uroot -phunter2 shop", "datetime": "2024-11-04 06:13:34"},
+    {"dir": "/home/user", "cmd": "mysql -uroot -phunter2 shop_test", "datetime": "2024-11-04 06:13:34"},
+    {"dir": "/home/user", "cmd": "git checkout 3f2a9c1b4e5d6f708192a3b4c5d6e7f8091a2b3c", "datetime": "2024-11-04 06:13:34"}
+]"#;
+
+#[test]
+fn secrets_are_redacted_before_sending() {
+    let root = setup_with_history("secrets_are_redacted_before_sending", HISTORY);
+    fs::write(root.join("work").join("Zx9Qm2Lp7Wv4Rt8Yb3Nc6Kd1.pem"), "").unwrap();
+    let (url, requests) = start_server("well_formed.json");
+
+    let output = run_with_stdin(
+        &root,
+        &[
+            "--backend",
+            "ollama",
+            "--url",
+            &url,
+            "--show-context",
+            QUERY,
+        ],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let request: Value = serde_json::from_str(&requests.lock().unwrap()[0]).unwrap();
+    let system_prompt = request["messages"][0]["content"].as_str().unwrap();
+    for secret in [
+        "wJalrXUtnFEMI",
+        "abc123def456",
+        "hunter2",
+        "Zx9Qm2Lp7Wv4Rt8Yb3Nc6Kd1",
+    ] {
+        assert!(!system_prompt.contains(secret), "{} was sent", secret);
+    }
+    assert!(system_prompt.contains("AWS_SECRET_ACCESS_KEY=<redacted_secret_"));
+    assert!(system_prompt.contains("Bearer <redacted_token_1>"));
+    // the same password gets the same placeholder every time
+    assert_eq!(
+        system_prompt
+            .matches("-p<redacted_password_1> shop")
+            .count(),
+        2
+    );
+    assert!(system_prompt.contains("3f2a9c1b4e5d6f708192a3b4c5d6e7f8091a2b3c"));
+
+    // what is printed is exactly what went out
+    assert!(stdout.contains(&format!("system:\n{}\n", system_prompt)));
+    assert!(stdout.contains(&format!("user:\n{}\n", QUERY)));
+}
+
+#[test]
+fn custom_rules_are_applied_and_restored_in_the_answer() {
+    let root = setup("custom_rules_are_applied_and_restored_in_the_answer");
+    fs::create_dir_all(root.join("home")).unwrap();
+    fs::write(
+        root.join("home").join("redact.toml"),
+        "[[rules]]\nname = \"customer\"\npattern = \"cust-[0-9]+\"\n",
+    )
+    .unwrap();
+    fs::write(root.join("work").join("cust-4711.csv"), "").unwrap();
+    let (url, requests) = start_server("redacted.json");
+
+    let output = run_with_stdin(&root, &["--backend", "ollama", "--url", &url, QUERY], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let request: Value = serde_json::from_str(&requests.lock().unwrap()[0]).unwrap();
+    let system_prompt = request["messages"][0]["content"].as_str().unwrap();
+    assert!(!system_prompt.contains("cust-4711"));
+    assert!(system_prompt.contains("\"name\":\"<redacted_customer_1>.csv\""));
+
+    // the placeholder the model used is swapped back before the suggestion is shown
+    assert!(stdout.contains("    cat cust-4711.csv\n"), "{}", stdout);
+}
@@ -0,0 +1,79 @@
+use std::fs;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn record_saves_request_and_response() {
+    let root = setup("record_saves_request_and_response");
+    run(&root, &["--record", &cassette_dir(&root)]);
+
+    let entries = fs::read_dir(cassette_dir(&root))
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(entries.len(), 1);
+
+    let path = entries[0].as_ref().unwrap().path();
+    let cassette: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        path.file_stem().unwrap().to_str().unwrap(),
+        cassette["key"].as_str().unwrap()
+    );
+    assert_eq!(cassette["request"]["messages"][1]["content"], QUERY);
+    assert!(cassette["response"].as_str().unwrap().contains("qwen2.5"));
+}
+
+#[test]
+fn replay_well_formed_response() {
+    let root = setup("replay_well_formed_response");
+    load_cassette(&root, "well_formed.json");
+
+    let output = run(&root, &["--replay", &cassette_dir(&root)]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("git commit -m <message>"), "{}", stderr);
+}
+
+#[test]
+fn replay_malformed_response() {
+    let root = setup("replay_malformed_response");
+    load_cassette(&root, "malformed.json");
+
+    // a clean error, not a panic
+    let output = run(&root, &["--replay", &cassette_dir(&root)]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(
+        stderr.contains("Unable to parse the model response: EOF while parsing"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn replay_empty_response() {
+    let root = setup("replay_empty_response");
+    load_cassette(&root, "empty.json");
+
+    let output = run(&root, &["--replay", &cassette_dir(&root)]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(
+        stderr.contains("model returned an empty response"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn replay_without_cassette() {
+    let root = setup("replay_without_cassette");
+
+    let output = run(&root, &["--replay", &cassette_dir(&root)]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no cassette for key"));
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::process::Command;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn selected_suggestion_is_written_to_a_script() {
+    let root = setup("selected_suggestion_is_written_to_a_script");
+    let (url, _) = start_server("fix.json");
+    let script = root.join("fix.sh");
+
+    let output = run_with_stdin(
+        &root,
+        &[
+            "--backend",
+            "ollama",
+            "--url",
+            &url,
+            "--emit-script",
+            script.to_str().unwrap(),
+            "fix",
+        ],
+        "n\n0\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("wrote script to"), "{}", stdout);
+    assert!(!stdout.contains("executing cmd"));
+
+    let content = fs::read_to_string(&script).unwrap();
+    assert!(content.starts_with("#!/usr/bin/env bash\n# psuh is a typo of push\n"));
+    assert!(content.contains("set -euo pipefail\n"));
+    assert!(content.contains("usage: $(basename \"$0\") [greeting]"));
+    assert!(
+        content.contains("'  greeting  what to write'"),
+        "{}",
+        content
+    );
+    assert!(content.contains("# step 1: writes the greeting\necho \"${greeting}\" > fixed.txt\n"));
+
+    // the script stops at the failing step just like the interactive run does
+    let run = Command::new(&script)
+        .arg("hi")
+        .current_dir(root.join("work"))
+        .output()
+        .unwrap();
+    assert!(!run.status.success());
+    assert_eq!(
+        fs::read_to_string(root.join("work").join("fixed.txt")).unwrap(),
+        "hi\n"
+    );
+    assert!(!root.join("work").join("never.txt").exists());
+
+    let help = Command::new(&script).arg("--help").output().unwrap();
+    assert!(String::from_utf8_lossy(&help.stdout).contains("e.g. hello - a friendly default"));
+}
+
+#[test]
+fn arguments_stay_one_word_in_the_script() {
+    let root = setup("arguments_stay_one_word_in_the_script");
+    let (url, _) = start_server("quoted.json");
+    let script = root.join("search.sh");
+
+    let output = run_with_stdin(
+        &root,
+        &[
+            "--backend",
+            "ollama",
+            "--url",
+            &url,
+            "--emit-script",
+            script.to_str().unwrap(),
+            "search",
+        ],
+        "0\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("wrote script to"), "{}", stdout);
+
+    let content = fs::read_to_string(&script).unwrap();
+    assert!(
+        content.contains("printf '%s|%s\\n' \"${pattern}\" \"in ${pattern}\" > found.txt\n"),
+        "{}",
+        content
+    );
+
+    // a glob in the work dir would match the * if the value was split and expanded
+    fs::write(root.join("work").join("b.txt"), "").unwrap();
+    let run = Command::new(&script)
+        .arg("a *b*")
+        .current_dir(root.join("work"))
+        .output()
+        .unwrap();
+    assert!(
+        run.status.success(),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("work").join("found.txt")).unwrap(),
+        "a *b*|in a *b*\n"
+    );
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+fn write_snippets(root: &Path) -> String {
+    let home_snippets = root.join("home").join("snippets");
+    fs::create_dir_all(&home_snippets).unwrap();
+    fs::write(
+        home_snippets.join("db.toml"),
+        r#"
+[[snippets]]
+description = "open a tunnel to the staging database"
+tags = ["db", "postgres"]
+cmd = "ssh -N -L 5432:<db_host>:5432 <bastion>"
+
+[[snippets.missing_fields]]
+key = "db_host"
+reasoning = "the database host behind the bastion"
+suggestions = [{ value = "staging-db.internal", reasoning = "the staging database" }]
+"#,
+    )
+    .unwrap();
+
+    let team_snippets = root.join("team");
+    fs::create_dir_all(&team_snippets).unwrap();
+    fs::write(
+        team_snippets.join("deploy.yaml"),
+        r#"
+snippets:
+  - description: deploy a service to staging
+    tags: [deploy, release]
+    cmd: ./scripts/deploy.sh --env staging <service>
+"#,
+    )
+    .unwrap();
+    fs::write(team_snippets.join("broken.yaml"), "snippets: [").unwrap();
+
+    team_snippets.to_str().unwrap().to_string()
+}
+
+#[test]
+fn matching_snippets_are_listed_with_the_suggestions() {
+    let root = setup("matching_snippets_are_listed_with_the_suggestions");
+    let team = write_snippets(&root);
+
+    let output = run_with_env(
+        &root,
+        &["--offline", "tunnel", "to", "the", "database"],
+        "",
+        &[("ZLI_TEAM_SNIPPETS", &team)],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("0 - snippet: open a tunnel to the staging database\n    ssh -N -L 5432:<db_host>:5432 <bastion>"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("deploy"));
+
+    let output = run_with_env(
+        &root,
+        &["--offline", "deploy", "staging"],
+        "0\nweb\n",
+        &[("ZLI_TEAM_SNIPPETS", &team)],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("0 - snippet: deploy a service to staging"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("value for service"));
+    assert!(stdout.contains("executing cmd ./scripts/deploy.sh --env staging web"));
+}
+
+#[test]
+fn matching_snippets_are_sent_as_examples() {
+    let root = setup("matching_snippets_are_sent_as_examples");
+    let team = write_snippets(&root);
+    let (url, requests) = start_server("well_formed.json");
+
+    let output = run_with_env(
+        &root,
+        &["--backend", "ollama", "--url", &url, "db", "tunnel"],
+        "",
+        &[("ZLI_TEAM_SNIPPETS", &team)],
+    );
+    assert!(output.status.success());
+
+    let request: Value = serde_json::from_str(&requests.lock().unwrap()[0]).unwrap();
+    let system_prompt = request["messages"][0]["content"].as_str().unwrap();
+    assert!(system_prompt.contains("### SNIPPETS\n[{"));
+    assert!(system_prompt.contains("staging-db.internal"));
+    assert!(!system_prompt.contains("deploy.sh"));
+}
@@ -0,0 +1,54 @@
+use std::fs;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn stats_flag_prints_usage() {
+    let root = setup("stats_flag_prints_usage");
+    load_cassette(&root, "well_formed.json");
+
+    let output = run(&root, &["--stats", "--replay", &cassette_dir(&root)]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(
+        stdout.contains("qwen2.5 | prompt 1834 tokens | 491 tokens at 9.39 tokens/sec"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn stats_subcommand_summarizes_per_model() {
+    let root = setup("stats_subcommand_summarizes_per_model");
+    let (url, _) = start_server("well_formed.json");
+    run(&root, &["--no-cache", "--backend", "ollama", "--url", &url]);
+    run(&root, &["--no-cache", "--backend", "ollama", "--url", &url]);
+
+    // the recording and replaying runs only exercise the backend, they are not usage
+    load_cassette(&root, "well_formed.json");
+    run(&root, &["--replay", &cassette_dir(&root)]);
+    run(&root, &[]);
+
+    let stats = fs::read_to_string(root.join("home").join("stats.jsonl")).unwrap();
+    assert_eq!(stats.lines().count(), 2);
+
+    let output = run_command(&root, &["stats"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    let row = stdout
+        .lines()
+        .find(|line| line.starts_with("qwen2.5"))
+        .unwrap();
+    assert_eq!(row.split_whitespace().nth(2), Some("2"), "{}", stdout);
+}
+
+#[test]
+fn stats_subcommand_without_usage() {
+    let root = setup("stats_subcommand_without_usage");
+
+    let output = run_command(&root, &["stats"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("no usage recorded yet"));
+}
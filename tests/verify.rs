@@ -0,0 +1,102 @@
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use std::{env, fs};
+
+use serde_json::Value;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn invalid_flags_are_sent_back_to_the_model() {
+    let root = setup("invalid_flags_are_sent_back_to_the_model");
+    let (url, requests) = start_server_with(&["unverified.json", "verified.json"]);
+
+    let output = run_with_stdin(&root, &["--backend", "ollama", "--url", &url, QUERY], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("0 - list all the files\n    ls -la | sort -r\n"),
+        "{}",
+        stdout
+    );
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let retry: Value = serde_json::from_str(&requests[1]).unwrap();
+    let messages = retry["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[2]["role"], "assistant");
+    let report = messages[3]["content"].as_str().unwrap();
+    assert!(
+        report.contains("`ls --no-such-flag-xyz`: --no-such-flag-xyz is not a flag of ls"),
+        "{}",
+        report
+    );
+    assert!(!report.contains("--run"));
+}
+
+#[test]
+fn valid_flags_are_not_sent_back() {
+    let root = setup("valid_flags_are_not_sent_back");
+    let (url, requests) = start_server("verified.json");
+
+    let output = run_with_stdin(&root, &["--backend", "ollama", "--url", &url, QUERY], "");
+    assert!(output.status.success());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn missing_binaries_are_annotated() {
+    let root = setup("missing_binaries_are_annotated");
+    let (url, requests) = start_server("unverified.json");
+
+    let output = run_with_stdin(
+        &root,
+        &["--backend", "ollama", "--url", &url, "--no-verify", QUERY],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "    not-a-real-binary-xyz --run\n      ! not-a-real-binary-xyz is not installed"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("! ls is not installed"));
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+// a program that isn't known to only print its usage is never run before the user picked it
+#[cfg(unix)]
+#[test]
+fn unknown_programs_are_not_run_for_their_help() {
+    let root = setup("unknown_programs_are_not_run_for_their_help");
+    let bin = root.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let probe = bin.join("zli-probe");
+    fs::write(
+        &probe,
+        format!("#!/bin/sh\necho \"$@\" > {}\n", root.join("ran").display()),
+    )
+    .unwrap();
+    fs::set_permissions(&probe, fs::Permissions::from_mode(0o755)).unwrap();
+    let (url, requests) = start_server("unlisted.json");
+
+    let path = format!("{}:{}", bin.display(), env::var("PATH").unwrap());
+    let output = run_with_env(
+        &root,
+        &["--backend", "ollama", "--url", &url, QUERY],
+        "",
+        &[("PATH", &path)],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("    zli-probe --all\n"), "{}", stdout);
+    assert!(!root.join("ran").exists());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}